@@ -0,0 +1,75 @@
+use symphonia::core::audio::Channels;
+
+// How a channel contributes to loudness measurements (ITU-R BS.1770)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelRole {
+    Front,
+    /// Left and right surround channels, weighted +1.5 dB
+    Surround,
+    /// Low-frequency effects, excluded from loudness
+    Lfe,
+}
+
+impl ChannelRole {
+    pub fn weight(self) -> f64 {
+        match self {
+            ChannelRole::Front => 1.0,
+            ChannelRole::Surround => 1.41,
+            ChannelRole::Lfe => 0.0,
+        }
+    }
+
+    // Roles in the usual WAV/SMPTE channel order when the file gives no layout
+    pub fn defaults(count: usize) -> Vec<ChannelRole> {
+        use ChannelRole::*;
+        match count {
+            4 => vec![Front, Front, Surround, Surround],
+            5 => vec![Front, Front, Front, Surround, Surround],
+            6.. => {
+                let mut roles = vec![Front, Front, Front, Lfe];
+                roles.resize(count, Surround);
+                roles
+            }
+            _ => vec![Front; count],
+        }
+    }
+
+    pub fn from_layout(channels: Channels) -> Vec<ChannelRole> {
+        let surround =
+            Channels::REAR_LEFT | Channels::REAR_RIGHT | Channels::SIDE_LEFT | Channels::SIDE_RIGHT;
+        channels
+            .iter()
+            .map(|channel| {
+                if channel.intersects(Channels::LFE1 | Channels::LFE2) {
+                    ChannelRole::Lfe
+                } else if channel.intersects(surround) {
+                    ChannelRole::Surround
+                } else {
+                    ChannelRole::Front
+                }
+            })
+            .collect()
+    }
+}
+
+// Decoded audio at full scale ([-1.0, 1.0]), one sample vector per channel
+pub struct DecodedAudio {
+    pub channels: Vec<Vec<f32>>,
+    pub roles: Vec<ChannelRole>,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    // Number of samples per channel
+    pub fn frames(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    // Average of the first two channels (left and right), as analysed for the spectrogram
+    pub fn mono(&self) -> Vec<f32> {
+        let used = &self.channels[..self.channels.len().min(2)];
+        (0..self.frames())
+            .map(|i| used.iter().map(|channel| channel[i]).sum::<f32>() / used.len() as f32)
+            .collect()
+    }
+}
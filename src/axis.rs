@@ -0,0 +1,329 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+// Candidate major tick steps in seconds, ascending
+const TIME_STEPS: &[f64] = &[
+    0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 20.0,
+    30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1200.0, 1800.0, 3600.0, 7200.0, 10800.0, 21600.0,
+];
+
+// Gap in pixels kept between neighbouring labels
+const LABEL_GAP: f32 = 16.0;
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrequencyUnit {
+    /// kHz when the displayed range reaches 2 kHz, Hz otherwise
+    Auto,
+    Hz,
+    Khz,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeFormat {
+    /// m:ss, or h:mm:ss for inputs of an hour or more
+    Auto,
+    /// Seconds with a unit, e.g. 1.5s
+    Seconds,
+    /// Milliseconds with a unit, e.g. 1500ms
+    Milliseconds,
+    /// m:ss with fractional seconds when needed
+    MinSec,
+    /// h:mm:ss with fractional seconds when needed
+    HourMinSec,
+    /// Sample index
+    Samples,
+    /// STFT frame index
+    Frames,
+}
+
+// Major ticks with labels and unlabelled minor ticks, as fractions along the axis
+pub struct AxisTicks {
+    pub major: Vec<(f32, String)>,
+    pub minor: Vec<f32>,
+}
+
+// Length of the time axis in the units of each format
+pub struct TimeExtent {
+    pub seconds: f64,
+    pub samples: usize,
+    pub frames: usize,
+}
+
+// Choose the smallest step whose labels fit the axis length without overlapping. Labels grow
+// with their value, so the first and last ticks stand in for the widest: measuring every tick
+// of every candidate step is far too slow on long inputs
+fn choose_step(
+    steps: &[f64],
+    (min, max): (f64, f64),
+    axis_length: f32,
+    label: &dyn Fn(f64, f64) -> String,
+    measure: &dyn Fn(&str) -> f32,
+) -> f64 {
+    for &step in steps {
+        let spacing = (step / (max - min)) as f32 * axis_length;
+        if spacing < LABEL_GAP {
+            continue;
+        }
+        let mut ticks = ticks_in(min, max, step);
+        let widest = ticks
+            .next()
+            .into_iter()
+            .chain(ticks.next_back())
+            .map(|value| measure(&label(value, step)))
+            .fold(0.0f32, f32::max);
+        if spacing >= widest + LABEL_GAP {
+            return step;
+        }
+    }
+    *steps.last().expect("No tick steps")
+}
+
+// Multiples of step from min up to max (inclusive, with rounding tolerance)
+fn ticks_in(min: f64, max: f64, step: f64) -> impl DoubleEndedIterator<Item = f64> {
+    let first = (min / step - 1e-9).ceil() as i64;
+    let last = (max / step + 1e-9).floor() as i64;
+    (first..=last).map(move |i| i as f64 * step)
+}
+
+// 1, 2, 5 x 10^k steps for integer-valued axes
+fn count_steps(range: f64) -> Vec<f64> {
+    decade_steps(1.0, range)
+}
+
+// 1, 2, 5 x 10^k steps starting at the given magnitude
+fn decade_steps(smallest: f64, range: f64) -> Vec<f64> {
+    let mut steps = Vec::new();
+    let mut magnitude = smallest;
+    while magnitude <= range.max(1.0) * 10.0 {
+        steps.extend([magnitude, magnitude * 2.0, magnitude * 5.0]);
+        magnitude *= 10.0;
+    }
+    steps
+}
+
+// Number of minor subdivisions that split a major step into steps from the same candidate set
+fn minor_divisions(step: f64, steps: &[f64]) -> usize {
+    [5, 4, 3, 2]
+        .into_iter()
+        .find(|&divisions| {
+            let minor = step / divisions as f64;
+            steps
+                .iter()
+                .any(|&candidate| (candidate - minor).abs() < minor * 1e-6)
+        })
+        .unwrap_or(1)
+}
+
+// Ticks for an axis running from min to max, given candidate steps and a label formatter
+fn linear_ticks(
+    steps: &[f64],
+    (min, max): (f64, f64),
+    axis_length: f32,
+    minor_ticks: bool,
+    label: &dyn Fn(f64, f64) -> String,
+    measure: &dyn Fn(&str) -> f32,
+) -> AxisTicks {
+    let range = max - min;
+    if range <= 0.0 {
+        return AxisTicks {
+            major: vec![(0.0, label(min, 1.0))],
+            minor: Vec::new(),
+        };
+    }
+
+    let step = choose_step(steps, (min, max), axis_length, label, measure);
+    let major = ticks_in(min, max, step)
+        .map(|value| (((value - min) / range) as f32, label(value, step)))
+        .collect();
+
+    let divisions = if minor_ticks {
+        minor_divisions(step, steps)
+    } else {
+        1
+    };
+    let minor_step = step / divisions as f64;
+    let minor = ticks_in(min, max, minor_step)
+        .filter(|value| ((value / step).round() * step - value).abs() > minor_step * 1e-6)
+        .map(|value| ((value - min) / range) as f32)
+        .collect();
+
+    AxisTicks { major, minor }
+}
+
+pub fn time_ticks(
+    extent: &TimeExtent,
+    format: TimeFormat,
+    axis_length: f32,
+    minor_ticks: bool,
+    measure: &dyn Fn(&str) -> f32,
+) -> AxisTicks {
+    match format {
+        TimeFormat::Samples | TimeFormat::Frames => {
+            let range = match format {
+                TimeFormat::Samples => extent.samples,
+                _ => extent.frames,
+            } as f64;
+            linear_ticks(
+                &count_steps(range),
+                (0.0, range),
+                axis_length,
+                minor_ticks,
+                &|value, _| format!("{}", value.round() as u64),
+                measure,
+            )
+        }
+        _ => {
+            let format = match format {
+                TimeFormat::Auto if extent.seconds >= 3600.0 => TimeFormat::HourMinSec,
+                TimeFormat::Auto => TimeFormat::MinSec,
+                other => other,
+            };
+            linear_ticks(
+                TIME_STEPS,
+                (0.0, extent.seconds),
+                axis_length,
+                minor_ticks,
+                &|value, step| format_time(value, step, format),
+                measure,
+            )
+        }
+    }
+}
+
+// Candidate colorbar steps in dB, finest first: 10 dB and coarser, or finer steps for ranges
+// narrower than 10 dB
+pub fn decibel_steps(range: f64) -> Vec<f64> {
+    decade_steps(if range >= 10.0 { 10.0 } else { 1.0 }, range)
+}
+
+// Labelled colorbar ticks from min to max, at the first of the candidate steps whose labels fit
+// the colorbar's length without overlapping
+pub fn colorbar_ticks(
+    steps: &[f64],
+    (min, max): (f64, f64),
+    length: f32,
+    label: &dyn Fn(f64) -> String,
+    measure: &dyn Fn(&str) -> f32,
+) -> Vec<(f32, String)> {
+    linear_ticks(
+        steps,
+        (min, max),
+        length,
+        false,
+        &|value, _| label(value),
+        measure,
+    )
+    .major
+}
+
+pub fn frequency_ticks(
+    (min_freq, max_freq): (f32, f32),
+    unit: FrequencyUnit,
+    axis_length: f32,
+    minor_ticks: bool,
+    measure: &dyn Fn(&str) -> f32,
+) -> AxisTicks {
+    let khz = uses_khz(unit, max_freq);
+    let range = (max_freq - min_freq) as f64;
+    linear_ticks(
+        &decade_steps(0.1, range),
+        (min_freq as f64, max_freq as f64),
+        axis_length,
+        minor_ticks,
+        &|value, step| format_frequency(value, decimals_for(step, khz), khz),
+        measure,
+    )
+}
+
+// Label for the top edge of the frequency axis (e.g. the Nyquist frequency), which is
+// usually not a multiple of the tick step
+pub fn frequency_edge_label(freq: f32, unit: FrequencyUnit) -> String {
+    let khz = uses_khz(unit, freq);
+    let value = if khz { freq / 1000.0 } else { freq };
+    // Up to two decimals without trailing zeros, keeping one for kHz as in 22.05kHz or 24.0kHz
+    let min_places = if khz { 1 } else { 0 };
+    let places = (min_places..=2)
+        .find(|&places| {
+            let scale = 10f32.powi(places);
+            ((value * scale).round() - value * scale).abs() < 1e-3
+        })
+        .unwrap_or(2) as usize;
+    format_frequency(freq as f64, places, khz)
+}
+
+fn uses_khz(unit: FrequencyUnit, max_freq: f32) -> bool {
+    match unit {
+        FrequencyUnit::Auto => max_freq >= 2000.0,
+        FrequencyUnit::Hz => false,
+        FrequencyUnit::Khz => true,
+    }
+}
+
+// Decimal places for ticks spaced `step` Hz apart, at least one for kHz as in 1.0kHz
+fn decimals_for(step: f64, khz: bool) -> usize {
+    if khz {
+        decimals(step / 1000.0).max(1)
+    } else {
+        decimals(step)
+    }
+}
+
+fn format_frequency(freq: f64, places: usize, khz: bool) -> String {
+    if khz {
+        format!("{:.*}kHz", places, freq / 1000.0)
+    } else {
+        format!("{:.*}Hz", places, freq)
+    }
+}
+
+// Decimal places needed to distinguish ticks spaced `step` apart
+fn decimals(step: f64) -> usize {
+    if step >= 1.0 {
+        0
+    } else {
+        (-step.log10() - 1e-9).ceil() as usize
+    }
+}
+
+fn format_time(seconds: f64, step: f64, format: TimeFormat) -> String {
+    let places = decimals(step);
+    // Round first so accumulated error cannot produce labels such as 0:60
+    let scale = 10f64.powi(places as i32 + if format_is_ms(format) { 3 } else { 0 });
+    let seconds = (seconds * scale).round() / scale;
+    match format {
+        TimeFormat::Seconds => format!("{:.*}s", places, seconds),
+        TimeFormat::Milliseconds => {
+            format!("{:.*}ms", decimals(step * 1000.0), seconds * 1000.0)
+        }
+        TimeFormat::HourMinSec => {
+            let whole = seconds.floor() as u64;
+            format!(
+                "{}:{:02}:{}",
+                whole / 3600,
+                (whole / 60) % 60,
+                format_seconds(seconds % 60.0, places)
+            )
+        }
+        _ => {
+            let whole = seconds.floor() as u64;
+            format!("{}:{}", whole / 60, format_seconds(seconds % 60.0, places))
+        }
+    }
+}
+
+fn format_is_ms(format: TimeFormat) -> bool {
+    matches!(format, TimeFormat::Milliseconds)
+}
+
+// Seconds within a minute, zero-padded to two integer digits
+fn format_seconds(seconds: f64, places: usize) -> String {
+    let width = if places > 0 { places + 3 } else { 2 };
+    format!(
+        "{:0width$.places$}",
+        seconds,
+        width = width,
+        places = places
+    )
+}
@@ -0,0 +1,717 @@
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+
+use crate::STDOUT_PATH;
+use crate::axis::{FrequencyUnit, TimeFormat};
+use crate::config::{Colormap, FrequencyLimit, Overrides, Settings, resolve_settings};
+use crate::features::Feature;
+use crate::layout::{Element, ImageSize};
+use crate::raw::{Endian, RawFormat, RawPcmSpec};
+use crate::stft::Window;
+use crate::theme::{Color, Theme};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    #[command(flatten)]
+    pub log: LogArgs,
+}
+
+impl Cli {
+    // Parse the command line, running `render` when no command is given so that a bare file
+    // (e.g. drag-and-drop) or render options without a command still work
+    pub fn parse_with_default_command() -> Self {
+        Cli::try_parse_with_default_command(std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    fn try_parse_with_default_command(
+        args: impl IntoIterator<Item = OsString>,
+    ) -> Result<Self, clap::Error> {
+        let mut args: Vec<OsString> = args.into_iter().collect();
+        let mut command = Cli::command();
+
+        // Only the global flags, which take no values, can come before a command
+        let globals: Vec<&clap::Arg> = command
+            .get_arguments()
+            .filter(|arg| arg.is_global_set())
+            .collect();
+        let is_global = |arg: &str| match arg.strip_prefix("--") {
+            Some(long) => globals.iter().any(|global| global.get_long() == Some(long)),
+            None => arg.strip_prefix('-').is_some_and(|shorts| {
+                !shorts.is_empty()
+                    && shorts.chars().all(|short| {
+                        globals
+                            .iter()
+                            .any(|global| global.get_short() == Some(short))
+                    })
+            }),
+        };
+        let starts_command = |arg: &str| {
+            matches!(arg, "help" | "-h" | "--help" | "-V" | "--version")
+                || command.get_subcommands().any(|subcommand| {
+                    subcommand.get_name() == arg
+                        || subcommand.get_all_aliases().any(|alias| alias == arg)
+                })
+        };
+
+        let position = args
+            .iter()
+            .skip(1)
+            .position(|arg| !is_global(&arg.to_string_lossy()))
+            .map_or(args.len(), |index| index + 1);
+        let has_command = args
+            .get(position)
+            .is_some_and(|arg| starts_command(&arg.to_string_lossy()));
+        if !has_command {
+            args.insert(position, "render".into());
+        }
+        let cli = Cli::try_parse_from(args)?;
+
+        // The JSON result and the output would be interleaved on stdout
+        if cli.log.json
+            && let Some(output) = cli.command.stdout_output()
+        {
+            let message = format!(
+                "--json cannot be combined with writing {} to stdout",
+                output
+            );
+            return Err(command.error(ErrorKind::ArgumentConflict, message));
+        }
+        Ok(cli)
+    }
+}
+
+// Verbosity and result format, accepted before or after the command
+#[derive(Args)]
+pub struct LogArgs {
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more details (-v for debug output, -vv for trace output)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Print a single JSON result on stdout instead of progress messages
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+impl LogArgs {
+    pub fn level(&self) -> log::LevelFilter {
+        match (self.quiet, self.json, self.verbose) {
+            (true, _, _) => log::LevelFilter::Error,
+            (false, _, 1) => log::LevelFilter::Debug,
+            (false, _, 2..) => log::LevelFilter::Trace,
+            (false, true, 0) => log::LevelFilter::Warn,
+            (false, false, 0) => log::LevelFilter::Info,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Render a spectrogram image (default when no command is given)
+    Render(RenderArgs),
+    /// Print container, codec, tag and artwork details of an audio file
+    Info(InfoArgs),
+    /// Export spectrogram magnitudes (dB) as CSV or JSON
+    Export(ExportArgs),
+    /// Render spectrograms for several files
+    Batch(BatchArgs),
+    /// Measure integrated loudness, loudness range and true peak (EBU R128)
+    Loudness(LoudnessArgs),
+    /// Report clipped runs, DC offset, digital silence and sample peaks
+    Qc(QcArgs),
+    /// Detect lossy transcodes and upsampling from the bandwidth cutoff
+    Lossy(LossyArgs),
+    /// Render the dB difference between the spectrograms of two files
+    Compare(CompareArgs),
+    /// Render several files into one image as a grid sharing axes and colorbar
+    Montage(MontageArgs),
+    /// Track the fundamental frequency (F0) and write time, frequency and confidence as CSV
+    Pitch(PitchArgs),
+    /// Export per-frame spectral features as CSV or JSON
+    Features(FeaturesArgs),
+    /// Detect onsets and beats and write their times as a list or label track
+    Onsets(OnsetsArgs),
+}
+
+impl Command {
+    // What the command writes to stdout when its output path is "-"
+    fn stdout_output(&self) -> Option<&'static str> {
+        let (output, written) = match self {
+            Command::Render(args) => (&args.output, "the image"),
+            Command::Compare(args) => (&args.output, "the image"),
+            Command::Montage(args) => (&args.output, "the image"),
+            Command::Export(args) => (&args.output, "the data"),
+            Command::Pitch(args) => (&args.output, "the data"),
+            Command::Features(args) => (&args.output, "the data"),
+            Command::Onsets(args) => (&args.output, "the data"),
+            _ => return None,
+        };
+        (output.as_deref() == Some(STDOUT_PATH)).then_some(written)
+    }
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Input audio file path (supports WAV, MP3, FLAC, OGG, AAC, etc.), "-" for stdin
+    #[arg(short, long, value_name = "FILE")]
+    pub input: Option<String>,
+
+    /// Input audio file (position argument, for drag-and-drop support)
+    #[arg(value_name = "INPUT_FILE")]
+    pub input_file: Option<String>,
+
+    /// Output spectrogram file path, "-" to write PNG data to stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// List the audio tracks in the input file and exit
+    #[arg(long)]
+    pub list_tracks: bool,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+// Decoding and STFT options shared by every command that analyses audio
+#[derive(Args, Clone)]
+pub struct AnalysisArgs {
+    /// FFT size (default: 4096)
+    #[arg(short, long)]
+    pub fft_size: Option<usize>,
+
+    /// Hop size (defaults to half of FFT size)
+    #[arg(short = 'p', long)]
+    pub hop_size: Option<usize>,
+
+    /// Window function (default: hann)
+    #[arg(short, long, value_enum)]
+    pub window: Option<Window>,
+
+    /// Colormap (default: turbo)
+    #[arg(short, long, value_enum)]
+    pub colormap: Option<Colormap>,
+
+    /// Lower bound of the dB range (default: -120)
+    #[arg(long, allow_negative_numbers = true)]
+    pub db_min: Option<f32>,
+
+    /// Upper bound of the dB range (default: 0)
+    #[arg(long, allow_negative_numbers = true)]
+    pub db_max: Option<f32>,
+
+    /// Lowest frequency to display in Hz (default: 0)
+    #[arg(long)]
+    pub freq_min: Option<f32>,
+
+    /// Highest frequency to display in Hz, or "nyquist" (defaults to the Nyquist frequency)
+    #[arg(long, value_name = "HZ")]
+    pub freq_max: Option<FrequencyLimit>,
+
+    /// Time axis label format (default: auto)
+    #[arg(long, value_enum)]
+    pub time_format: Option<TimeFormat>,
+
+    /// Frequency axis unit (default: auto)
+    #[arg(long, value_enum)]
+    pub freq_unit: Option<FrequencyUnit>,
+
+    /// Hide minor ticks between labelled axis ticks
+    #[arg(long)]
+    pub no_minor_ticks: bool,
+
+    /// Draw horizontal grid lines at the frequency ticks
+    #[arg(long)]
+    pub grid: bool,
+
+    /// Size of axis labels in pixels (default: 24)
+    #[arg(long)]
+    pub font_size: Option<f32>,
+
+    /// Blank border around the image in pixels (default: 20)
+    #[arg(long)]
+    pub margin: Option<u32>,
+
+    /// Elements to leave out of the image, comma-separated
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "ELEMENTS")]
+    pub hide: Vec<Element>,
+
+    /// Output only the spectrogram pixels, one per frame and frequency bin, without
+    /// margins, axes, labels or colorbar
+    #[arg(long, visible_alias = "raw-image")]
+    pub no_axes: bool,
+
+    /// Resample the bare spectrogram to WIDTHxHEIGHT pixels
+    #[arg(long, value_name = "WIDTHxHEIGHT", requires = "no_axes")]
+    pub size: Option<ImageSize>,
+
+    /// Color theme of the image (default: light)
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+
+    /// Background color as a CSS color, e.g. "#1e1e1e" or "#00000000" for transparent
+    #[arg(long, value_name = "COLOR")]
+    pub background: Option<Color>,
+
+    /// Color of axis lines, tick marks and the colorbar border
+    #[arg(long, value_name = "COLOR")]
+    pub axis_color: Option<Color>,
+
+    /// Color of labels
+    #[arg(long, value_name = "COLOR")]
+    pub text_color: Option<Color>,
+
+    /// Color blended into the plot for grid lines
+    #[arg(long, value_name = "COLOR")]
+    pub grid_color: Option<Color>,
+
+    /// Draw the waveform (peak and RMS envelope) in a panel above the spectrogram
+    #[arg(long)]
+    pub waveform: bool,
+
+    /// Height of the waveform panel in pixels (default: 150)
+    #[arg(long, value_name = "PX")]
+    pub waveform_height: Option<u32>,
+
+    /// Measure loudness (EBU R128) and draw momentary and short-term loudness in a panel above
+    /// the spectrogram
+    #[arg(long)]
+    pub loudness: bool,
+
+    /// Height of the loudness panel in pixels (default: 150)
+    #[arg(long, value_name = "PX")]
+    pub loudness_height: Option<u32>,
+
+    /// Check for clipped runs, DC offset and digital silence, and mark problems along the
+    /// bottom edge of the spectrogram
+    #[arg(long)]
+    pub qc_markers: bool,
+
+    /// Detect lossy transcodes and upsampling from the bandwidth cutoff, and trace the cutoff
+    /// over the spectrogram
+    #[arg(long)]
+    pub lossy_check: bool,
+
+    /// Track the fundamental frequency (YIN) and draw its contour over the spectrogram
+    #[arg(long)]
+    pub pitch: bool,
+
+    /// Lowest fundamental frequency searched by the pitch tracker in Hz (default: 50)
+    #[arg(long, value_name = "HZ")]
+    pub pitch_min: Option<f32>,
+
+    /// Highest fundamental frequency searched by the pitch tracker in Hz (default: 1000)
+    #[arg(long, value_name = "HZ")]
+    pub pitch_max: Option<f32>,
+
+    /// Trace the spectral centroid (white) and rolloff (black) over the spectrogram
+    #[arg(long)]
+    pub feature_overlay: bool,
+
+    /// Plot a spectral feature in a panel above the spectrogram
+    #[arg(long, value_enum, value_name = "FEATURE")]
+    pub feature_panel: Option<Feature>,
+
+    /// Height of the feature panel in pixels (default: 150)
+    #[arg(long, value_name = "PX")]
+    pub feature_height: Option<u32>,
+
+    /// Detect note onsets (spectral flux) and draw them as vertical lines over the spectrogram
+    #[arg(long)]
+    pub onsets: bool,
+
+    /// Estimate the tempo and draw the tracked beats as dashed vertical lines over the
+    /// spectrogram
+    #[arg(long)]
+    pub beats: bool,
+
+    /// Draw the average (mean and max-hold) spectrum in a panel right of the spectrogram
+    #[arg(long)]
+    pub spectrum: bool,
+
+    /// Width of the average spectrum panel in pixels (default: 200)
+    #[arg(long, value_name = "PX")]
+    pub spectrum_width: Option<u32>,
+
+    /// Named preset from the config files or built in: speech, music-hires, birdsong
+    #[arg(short = 'P', long)]
+    pub preset: Option<String>,
+
+    /// Config file to use instead of the user-level and project-local AudioSpectrogram.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<String>,
+
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+}
+
+// Options controlling how the input is decoded
+#[derive(Args, Clone)]
+pub struct DecodeArgs {
+    /// Audio track to decode: index among audio tracks, "id:<track id>" or a language code
+    #[arg(short, long, value_name = "TRACK")]
+    pub track: Option<String>,
+
+    /// Treat the input as headerless PCM described by the --sample-rate, --channels,
+    /// --format and --endian options
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Sample rate of raw input in Hz
+    #[arg(long, requires = "raw")]
+    pub sample_rate: Option<u32>,
+
+    /// Channel count of raw input
+    #[arg(long, default_value_t = 1, requires = "raw")]
+    pub channels: usize,
+
+    /// Sample format of raw input
+    #[arg(long = "format", value_enum, default_value_t = RawFormat::S16le, requires = "raw")]
+    pub raw_format: RawFormat,
+
+    /// Byte order of raw input (defaults to little-endian)
+    #[arg(long, value_enum, requires = "raw")]
+    pub endian: Option<Endian>,
+}
+
+impl DecodeArgs {
+    pub fn raw_spec(&self) -> Result<Option<RawPcmSpec>, Box<dyn std::error::Error>> {
+        if !self.raw {
+            return Ok(None);
+        }
+
+        let sample_rate = self.sample_rate.ok_or("Raw input requires --sample-rate")?;
+        Ok(Some(RawPcmSpec {
+            sample_rate,
+            channels: self.channels,
+            format: self.raw_format,
+            endian: self.endian.unwrap_or(Endian::Little),
+        }))
+    }
+}
+
+impl AnalysisArgs {
+    // Combine config files, the selected preset and explicit arguments
+    pub fn settings(&self) -> Result<Settings, Box<dyn std::error::Error>> {
+        let overrides = Overrides {
+            fft_size: self.fft_size,
+            hop_size: self.hop_size,
+            window: self.window,
+            colormap: self.colormap,
+            db_min: self.db_min,
+            db_max: self.db_max,
+            freq_min: self.freq_min,
+            freq_max: self.freq_max,
+            time_format: self.time_format,
+            freq_unit: self.freq_unit,
+            minor_ticks: self.no_minor_ticks.then_some(false),
+            grid: self.grid.then_some(true),
+            font_size: self.font_size,
+            margin: self.margin,
+            hide: (!self.hide.is_empty()).then(|| self.hide.clone()),
+            no_axes: self.no_axes.then_some(true),
+            size: self.size,
+            theme: self.theme,
+            background: self.background,
+            axis_color: self.axis_color,
+            text_color: self.text_color,
+            grid_color: self.grid_color,
+            waveform: self.waveform.then_some(true),
+            waveform_height: self.waveform_height,
+            loudness: self.loudness.then_some(true),
+            loudness_height: self.loudness_height,
+            qc_markers: self.qc_markers.then_some(true),
+            lossy_check: self.lossy_check.then_some(true),
+            pitch: self.pitch.then_some(true),
+            pitch_min: self.pitch_min,
+            pitch_max: self.pitch_max,
+            feature_overlay: self.feature_overlay.then_some(true),
+            feature_panel: self.feature_panel,
+            feature_height: self.feature_height,
+            onsets: self.onsets.then_some(true),
+            beats: self.beats.then_some(true),
+            spectrum: self.spectrum.then_some(true),
+            spectrum_width: self.spectrum_width,
+        };
+        resolve_settings(self.config.as_deref(), self.preset.as_deref(), &overrides)
+    }
+}
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+}
+
+#[derive(Args)]
+pub struct LoudnessArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+}
+
+#[derive(Args)]
+pub struct QcArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+}
+
+#[derive(Args)]
+pub struct LossyArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+}
+
+// A dB value above zero, e.g. for the symmetric range of a difference colorbar
+fn positive_decibels(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(db) if db.is_finite() && db > 0.0 => Ok(db),
+        Ok(_) => Err("must be a positive number of dB".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Reference audio file path
+    #[arg(value_name = "REFERENCE")]
+    pub reference: String,
+
+    /// Audio file path compared against the reference
+    #[arg(value_name = "COMPARED")]
+    pub compared: String,
+
+    /// Output image path (defaults to the compared filename with ".diff.png"), "-" for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Align the inputs by cross-correlation before comparing
+    #[arg(long)]
+    pub align: bool,
+
+    /// Largest offset searched when aligning, in seconds
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        requires = "align"
+    )]
+    pub max_offset: f64,
+
+    /// Difference at either end of the colorbar in dB
+    #[arg(long, value_name = "DB", default_value_t = 20.0, value_parser = positive_decibels)]
+    pub diff_range: f32,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Args)]
+pub struct MontageArgs {
+    /// Input audio file paths, placed left to right and top to bottom
+    #[arg(value_name = "FILES", required = true)]
+    pub inputs: Vec<String>,
+
+    /// Output image path (default: montage.png), "-" to write PNG data to stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Number of columns (defaults to a near-square grid; the file count puts them side by side)
+    #[arg(long, value_name = "N")]
+    pub columns: Option<usize>,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Args)]
+pub struct PitchArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    /// Output CSV file path (defaults to input filename with ".pitch.csv"), "-" for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Args)]
+pub struct FeaturesArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    /// Output data file path (defaults to input filename with ".features" and the format's
+    /// extension), "-" for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Output data format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub data_format: ExportFormat,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Args)]
+pub struct OnsetsArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    /// Output file path (defaults to input filename with ".onsets.txt", or ".beats.txt" with
+    /// --beats), "-" for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Write the tracked beats instead of the onsets
+    #[arg(long)]
+    pub beats: bool,
+
+    /// Output format of the marker times
+    #[arg(long, value_enum, default_value_t = MarkerFormat::Text)]
+    pub marker_format: MarkerFormat,
+
+    #[command(flatten)]
+    pub decode: DecodeArgs,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Input audio file path, "-" for stdin
+    #[arg(value_name = "FILE")]
+    pub input: String,
+
+    /// Output data file path (defaults to input filename with the format's extension), "-" for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Output data format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    pub data_format: ExportFormat,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MarkerFormat {
+    /// One time in seconds per line
+    Text,
+    /// Audacity label track: start, end and label separated by tabs
+    Labels,
+}
+
+#[derive(Args)]
+pub struct BatchArgs {
+    /// Input audio file paths
+    #[arg(value_name = "FILES", required = true)]
+    pub inputs: Vec<String>,
+
+    /// Directory for the generated images (defaults to the current directory)
+    #[arg(short = 'd', long, value_name = "DIR")]
+    pub output_dir: Option<String>,
+
+    #[command(flatten)]
+    pub analysis: AnalysisArgs,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        let args = std::iter::once("AudioSpectrogram").chain(args.iter().copied());
+        Cli::try_parse_with_default_command(args.map(OsString::from))
+    }
+
+    fn rendered_input(args: &[&str]) -> String {
+        match parse(args).expect("Rejected command line").command {
+            Command::Render(render) => render.input.or(render.input_file).expect("No input"),
+            _ => panic!("{:?} did not run render", args),
+        }
+    }
+
+    #[test]
+    fn bare_file_runs_render() {
+        assert_eq!(rendered_input(&["song.flac"]), "song.flac");
+    }
+
+    #[test]
+    fn global_flags_can_come_before_the_command() {
+        let cli = parse(&["--json", "-v", "loudness", "song.flac"]).expect("Rejected");
+        assert!(cli.log.json);
+        assert_eq!(cli.log.verbose, 1);
+        assert!(matches!(cli.command, Command::Loudness(_)));
+
+        let cli = parse(&["-q", "song.flac"]).expect("Rejected");
+        assert!(cli.log.quiet);
+        assert!(matches!(cli.command, Command::Render(_)));
+    }
+
+    #[test]
+    fn render_options_without_a_command_run_render() {
+        assert_eq!(
+            rendered_input(&["--fft-size", "1024", "song.flac"]),
+            "song.flac"
+        );
+        assert_eq!(rendered_input(&["-i", "song.flac", "--json"]), "song.flac");
+    }
+
+    #[test]
+    fn file_named_like_a_command_needs_render() {
+        assert_eq!(rendered_input(&["render", "info"]), "info");
+        assert!(matches!(
+            parse(&["info", "song.flac"]).expect("Rejected").command,
+            Command::Info(_)
+        ));
+    }
+
+    #[test]
+    fn json_conflicts_with_stdout_output() {
+        let error = parse(&["--json", "song.flac", "-o", "-"])
+            .err()
+            .expect("Accepted");
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        assert!(parse(&["--json", "song.flac", "-o", "song.png"]).is_ok());
+        assert!(parse(&["--json", "pitch", "song.flac", "-o", "-"]).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use rustfft::{FftPlanner, num_complex::Complex};
+use serde::Serialize;
+
+use crate::magnitude_to_db;
+
+// Only the start of both signals is cross-correlated to find their offset
+const ALIGN_SECONDS: f64 = 30.0;
+
+// Result of a comparison, printed as JSON with --json
+#[derive(Serialize)]
+pub struct CompareReport {
+    pub reference: String,
+    pub compared: String,
+    pub output: String,
+    pub sample_rate: u32,
+    /// Samples skipped at the start of the compared input; negative when the reference is skipped
+    pub offset_samples: i64,
+    pub offset_seconds: f64,
+    pub frames: usize,
+    /// RMS of the plotted dB differences
+    pub rms_difference_db: f64,
+}
+
+// Lag of `compared` relative to `reference` in samples, within ±max_lag, at the peak of their
+// cross-correlation. A positive lag means `compared` starts later.
+pub fn find_offset(reference: &[f32], compared: &[f32], max_lag: usize, sample_rate: u32) -> i64 {
+    let length = (ALIGN_SECONDS * sample_rate as f64) as usize;
+    let reference = &reference[..reference.len().min(length)];
+    let compared = &compared[..compared.len().min(length + max_lag)];
+    if reference.is_empty() || compared.is_empty() {
+        return 0;
+    }
+
+    // Zero-padded so the circular correlation holds every lag in range
+    let size = (reference.len() + compared.len()).next_power_of_two();
+    let spectrum = |signal: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = signal.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        buffer.resize(size, Complex::new(0.0, 0.0));
+        FftPlanner::new()
+            .plan_fft_forward(size)
+            .process(&mut buffer);
+        buffer
+    };
+    let reference = spectrum(reference);
+    let mut correlation: Vec<Complex<f32>> = spectrum(compared)
+        .iter()
+        .zip(&reference)
+        .map(|(c, r)| c * r.conj())
+        .collect();
+    FftPlanner::new()
+        .plan_fft_inverse(size)
+        .process(&mut correlation);
+
+    // Lag k sits at index k, negative lags wrap around to the end
+    let max_lag = max_lag.min(size / 2 - 1) as i64;
+    (-max_lag..=max_lag)
+        .max_by(|&a, &b| {
+            let at = |lag: i64| correlation[lag.rem_euclid(size as i64) as usize].re;
+            at(a).total_cmp(&at(b))
+        })
+        .unwrap_or(0)
+}
+
+// Per-bin dB difference (compared minus reference), both floored at `floor_db` so that bins
+// below the plotted range do not dominate
+pub fn difference_frames(
+    reference: &[Vec<f32>],
+    compared: &[Vec<f32>],
+    floor_db: f32,
+) -> Vec<Vec<f32>> {
+    reference
+        .iter()
+        .zip(compared)
+        .map(|(r, c)| {
+            r.iter()
+                .zip(c)
+                .map(|(&r, &c)| magnitude_to_db(c).max(floor_db) - magnitude_to_db(r).max(floor_db))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn rms(frames: &[Vec<f32>], bins: &std::ops::Range<usize>) -> f64 {
+    let (sum, count) = frames.iter().fold((0.0f64, 0usize), |(sum, count), frame| {
+        let squares: f64 = frame[bins.clone()]
+            .iter()
+            .map(|&d| (d as f64).powi(2))
+            .sum();
+        (sum + squares, count + bins.len())
+    });
+    (sum / count.max(1) as f64).sqrt()
+}
+
+// Blue where the compared input is quieter, red where it is louder, white where they match
+pub fn difference_gradient() -> colorgrad::Gradient {
+    colorgrad::CustomGradient::new()
+        .html_colors(&[
+            "#053061", "#2166ac", "#4393c3", "#92c5de", "#d1e5f0", "#f7f7f7", "#fddbc7", "#f4a582",
+            "#d6604d", "#b2182b", "#67001f",
+        ])
+        .build()
+        .expect("Invalid difference gradient")
+}
+
+// Candidate colorbar steps, finest first: the coarsest step that still gives at least four
+// labels on each side of 0 dB, then coarser ones for short colorbars
+pub fn difference_steps(range_db: f32) -> Vec<f64> {
+    const STEPS: [f64; 9] = [0.5, 1.0, 2.0, 3.0, 5.0, 10.0, 20.0, 30.0, 50.0];
+    let finest = STEPS
+        .iter()
+        .rposition(|&step| step >= 1.0 && range_db as f64 / step >= 4.0)
+        .unwrap_or(0);
+    STEPS[finest..].to_vec()
+}
+
+// Signed colorbar label, e.g. -6dB, 0dB or +6dB
+pub fn difference_label(db: f64) -> String {
+    if db == 0.0 {
+        "0dB".to_string()
+    } else {
+        format!("{:+}dB", db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::white_noise;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    // `signal` starting `delay` samples late, with silence before it
+    fn delayed(signal: &[f32], delay: usize) -> Vec<f32> {
+        let mut shifted = vec![0.0; delay];
+        shifted.extend_from_slice(signal);
+        shifted
+    }
+
+    #[test]
+    fn later_compared_input_has_a_positive_offset() {
+        let reference = white_noise(SAMPLE_RATE as usize);
+        let compared = delayed(&reference, 1234);
+        assert_eq!(find_offset(&reference, &compared, 4410, SAMPLE_RATE), 1234);
+    }
+
+    #[test]
+    fn later_reference_has_a_negative_offset() {
+        let compared = white_noise(SAMPLE_RATE as usize);
+        let reference = delayed(&compared, 567);
+        assert_eq!(find_offset(&reference, &compared, 4410, SAMPLE_RATE), -567);
+    }
+}
@@ -0,0 +1,618 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::axis::{FrequencyUnit, TimeFormat};
+use crate::features::Feature;
+use crate::layout::{Element, ImageSize, LayoutOptions};
+use crate::stft::Window;
+use crate::theme::{Color, Palette, Theme};
+
+// Project-local config file, looked up in the current directory
+const PROJECT_CONFIG_FILE: &str = "AudioSpectrogram.toml";
+
+// Upper frequency bound given as a number in Hz or "nyquist", so that a preset or the command
+// line can reset a bound set by a config file
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "FrequencyLimitValue")]
+pub enum FrequencyLimit {
+    Nyquist,
+    Hz(f32),
+}
+
+// Either form of a frequency limit in a config file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrequencyLimitValue {
+    Hz(f32),
+    Name(String),
+}
+
+impl std::str::FromStr for FrequencyLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("nyquist") {
+            return Ok(FrequencyLimit::Nyquist);
+        }
+        s.trim()
+            .parse()
+            .map(FrequencyLimit::Hz)
+            .map_err(|_| format!("Invalid frequency \"{}\", expected Hz or \"nyquist\"", s))
+    }
+}
+
+impl TryFrom<FrequencyLimitValue> for FrequencyLimit {
+    type Error = String;
+
+    fn try_from(value: FrequencyLimitValue) -> Result<Self, Self::Error> {
+        match value {
+            FrequencyLimitValue::Hz(hz) => Ok(FrequencyLimit::Hz(hz)),
+            FrequencyLimitValue::Name(name) => name.parse(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Colormap {
+    Turbo,
+    Viridis,
+    Inferno,
+    Magma,
+    Plasma,
+    Cividis,
+    Grayscale,
+}
+
+impl Colormap {
+    pub fn gradient(self) -> colorgrad::Gradient {
+        match self {
+            Colormap::Turbo => colorgrad::turbo(),
+            Colormap::Viridis => colorgrad::viridis(),
+            Colormap::Inferno => colorgrad::inferno(),
+            Colormap::Magma => colorgrad::magma(),
+            Colormap::Plasma => colorgrad::plasma(),
+            Colormap::Cividis => colorgrad::cividis(),
+            Colormap::Grayscale => colorgrad::CustomGradient::new()
+                .html_colors(&["#000000", "#ffffff"])
+                .build()
+                .expect("Invalid grayscale gradient"),
+        }
+    }
+}
+
+// Fully resolved analysis and rendering parameters
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub fft_size: usize,
+    pub hop_size: usize,
+    pub window: Window,
+    pub colormap: Colormap,
+    pub db_min: f32,
+    pub db_max: f32,
+    pub freq_min: f32,
+    /// Upper frequency bound in Hz, `None` for the Nyquist frequency
+    pub freq_max: Option<f32>,
+    pub time_format: TimeFormat,
+    pub freq_unit: FrequencyUnit,
+    pub minor_ticks: bool,
+    pub grid: bool,
+    /// Size of axis labels in pixels; colorbar labels are drawn slightly smaller
+    pub font_size: f32,
+    /// Blank border around the image in pixels
+    pub margin: u32,
+    pub hide: Vec<Element>,
+    /// Output only the plot area, without margins, axes or colorbar
+    pub no_axes: bool,
+    /// Resampled size of the bare plot, `None` for one pixel per frame and bin
+    pub size: Option<ImageSize>,
+    pub theme: Theme,
+    /// Colors replacing those of the theme
+    pub background: Option<Color>,
+    pub axis_color: Option<Color>,
+    pub text_color: Option<Color>,
+    pub grid_color: Option<Color>,
+    /// Draw the amplitude envelope in a panel above the spectrogram
+    pub waveform: bool,
+    pub waveform_height: u32,
+    /// Measure loudness and draw its curves in a panel above the spectrogram
+    pub loudness: bool,
+    pub loudness_height: u32,
+    /// Check for clipping, DC offset and silence, marking problems along the time axis
+    pub qc_markers: bool,
+    /// Detect lossy transcodes and upsampling, tracing the bandwidth cutoff over the plot
+    pub lossy_check: bool,
+    /// Track the fundamental frequency and draw its contour over the plot
+    pub pitch: bool,
+    /// Search range of the pitch tracker in Hz
+    pub pitch_min: f32,
+    pub pitch_max: f32,
+    /// Trace the spectral centroid and rolloff over the plot
+    pub feature_overlay: bool,
+    /// Feature plotted in a panel above the spectrogram
+    pub feature_panel: Option<Feature>,
+    pub feature_height: u32,
+    /// Detect onsets and draw them as vertical lines over the plot
+    pub onsets: bool,
+    /// Track beats and draw them as dashed vertical lines over the plot
+    pub beats: bool,
+    /// Draw the mean and max-hold spectrum in a panel right of the spectrogram
+    pub spectrum: bool,
+    pub spectrum_width: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fft_size: 4096,
+            hop_size: 2048,
+            window: Window::Hann,
+            colormap: Colormap::Turbo,
+            db_min: -120.0,
+            db_max: 0.0,
+            freq_min: 0.0,
+            freq_max: None,
+            time_format: TimeFormat::Auto,
+            freq_unit: FrequencyUnit::Auto,
+            minor_ticks: true,
+            grid: false,
+            font_size: 24.0,
+            margin: 20,
+            hide: Vec::new(),
+            no_axes: false,
+            size: None,
+            theme: Theme::Light,
+            background: None,
+            axis_color: None,
+            text_color: None,
+            grid_color: None,
+            waveform: false,
+            waveform_height: 150,
+            loudness: false,
+            loudness_height: 150,
+            qc_markers: false,
+            lossy_check: false,
+            pitch: false,
+            pitch_min: 50.0,
+            pitch_max: 1000.0,
+            feature_overlay: false,
+            feature_panel: None,
+            feature_height: 150,
+            onsets: false,
+            beats: false,
+            spectrum: false,
+            spectrum_width: 200,
+        }
+    }
+}
+
+impl Settings {
+    pub fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            font_size: self.font_size,
+            margin: self.margin,
+            hidden: self.hide.clone(),
+            waveform_height: self.waveform.then_some(self.waveform_height),
+            loudness_height: self.loudness.then_some(self.loudness_height),
+            feature_height: self.feature_panel.map(|_| self.feature_height),
+            spectrum_width: self.spectrum.then_some(self.spectrum_width),
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.theme.palette();
+        let overrides = [
+            (&mut palette.background, self.background),
+            (&mut palette.axes, self.axis_color),
+            (&mut palette.text, self.text_color),
+            (&mut palette.grid, self.grid_color),
+        ];
+        for (color, value) in overrides {
+            if let Some(Color(value)) = value {
+                *color = value;
+            }
+        }
+        palette
+    }
+}
+
+// Partial settings as found in a config file section, a preset or on the command line
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overrides {
+    pub fft_size: Option<usize>,
+    pub hop_size: Option<usize>,
+    pub window: Option<Window>,
+    pub colormap: Option<Colormap>,
+    pub db_min: Option<f32>,
+    pub db_max: Option<f32>,
+    pub freq_min: Option<f32>,
+    pub freq_max: Option<FrequencyLimit>,
+    pub time_format: Option<TimeFormat>,
+    pub freq_unit: Option<FrequencyUnit>,
+    pub minor_ticks: Option<bool>,
+    pub grid: Option<bool>,
+    pub font_size: Option<f32>,
+    pub margin: Option<u32>,
+    pub hide: Option<Vec<Element>>,
+    pub no_axes: Option<bool>,
+    pub size: Option<ImageSize>,
+    pub theme: Option<Theme>,
+    pub background: Option<Color>,
+    pub axis_color: Option<Color>,
+    pub text_color: Option<Color>,
+    pub grid_color: Option<Color>,
+    pub waveform: Option<bool>,
+    pub waveform_height: Option<u32>,
+    pub loudness: Option<bool>,
+    pub loudness_height: Option<u32>,
+    pub qc_markers: Option<bool>,
+    pub lossy_check: Option<bool>,
+    pub pitch: Option<bool>,
+    pub pitch_min: Option<f32>,
+    pub pitch_max: Option<f32>,
+    pub feature_overlay: Option<bool>,
+    pub feature_panel: Option<Feature>,
+    pub feature_height: Option<u32>,
+    pub onsets: Option<bool>,
+    pub beats: Option<bool>,
+    pub spectrum: Option<bool>,
+    pub spectrum_width: Option<u32>,
+}
+
+impl Overrides {
+    // Apply every value that is set; the hop size follows the FFT size unless given explicitly
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(fft_size) = self.fft_size {
+            settings.fft_size = fft_size;
+            settings.hop_size = fft_size / 2;
+        }
+        if let Some(hop_size) = self.hop_size {
+            settings.hop_size = hop_size;
+        }
+        if let Some(window) = self.window {
+            settings.window = window;
+        }
+        if let Some(colormap) = self.colormap {
+            settings.colormap = colormap;
+        }
+        if let Some(db_min) = self.db_min {
+            settings.db_min = db_min;
+        }
+        if let Some(db_max) = self.db_max {
+            settings.db_max = db_max;
+        }
+        if let Some(freq_min) = self.freq_min {
+            settings.freq_min = freq_min;
+        }
+        if let Some(freq_max) = self.freq_max {
+            settings.freq_max = match freq_max {
+                FrequencyLimit::Nyquist => None,
+                FrequencyLimit::Hz(hz) => Some(hz),
+            };
+        }
+        if let Some(time_format) = self.time_format {
+            settings.time_format = time_format;
+        }
+        if let Some(freq_unit) = self.freq_unit {
+            settings.freq_unit = freq_unit;
+        }
+        if let Some(minor_ticks) = self.minor_ticks {
+            settings.minor_ticks = minor_ticks;
+        }
+        if let Some(grid) = self.grid {
+            settings.grid = grid;
+        }
+        if let Some(font_size) = self.font_size {
+            settings.font_size = font_size;
+        }
+        if let Some(margin) = self.margin {
+            settings.margin = margin;
+        }
+        if let Some(hide) = &self.hide {
+            settings.hide = hide.clone();
+        }
+        if let Some(no_axes) = self.no_axes {
+            settings.no_axes = no_axes;
+        }
+        if let Some(size) = self.size {
+            settings.size = Some(size);
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+        }
+        if let Some(background) = self.background {
+            settings.background = Some(background);
+        }
+        if let Some(axis_color) = self.axis_color {
+            settings.axis_color = Some(axis_color);
+        }
+        if let Some(text_color) = self.text_color {
+            settings.text_color = Some(text_color);
+        }
+        if let Some(grid_color) = self.grid_color {
+            settings.grid_color = Some(grid_color);
+        }
+        if let Some(waveform) = self.waveform {
+            settings.waveform = waveform;
+        }
+        if let Some(waveform_height) = self.waveform_height {
+            settings.waveform_height = waveform_height;
+        }
+        if let Some(loudness) = self.loudness {
+            settings.loudness = loudness;
+        }
+        if let Some(loudness_height) = self.loudness_height {
+            settings.loudness_height = loudness_height;
+        }
+        if let Some(qc_markers) = self.qc_markers {
+            settings.qc_markers = qc_markers;
+        }
+        if let Some(lossy_check) = self.lossy_check {
+            settings.lossy_check = lossy_check;
+        }
+        if let Some(pitch) = self.pitch {
+            settings.pitch = pitch;
+        }
+        if let Some(pitch_min) = self.pitch_min {
+            settings.pitch_min = pitch_min;
+        }
+        if let Some(pitch_max) = self.pitch_max {
+            settings.pitch_max = pitch_max;
+        }
+        if let Some(feature_overlay) = self.feature_overlay {
+            settings.feature_overlay = feature_overlay;
+        }
+        if let Some(feature_panel) = self.feature_panel {
+            settings.feature_panel = Some(feature_panel);
+        }
+        if let Some(feature_height) = self.feature_height {
+            settings.feature_height = feature_height;
+        }
+        if let Some(onsets) = self.onsets {
+            settings.onsets = onsets;
+        }
+        if let Some(beats) = self.beats {
+            settings.beats = beats;
+        }
+        if let Some(spectrum) = self.spectrum {
+            settings.spectrum = spectrum;
+        }
+        if let Some(spectrum_width) = self.spectrum_width {
+            settings.spectrum_width = spectrum_width;
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub defaults: Overrides,
+    pub presets: BTreeMap<String, Overrides>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+    }
+}
+
+fn builtin_preset(name: &str) -> Option<Overrides> {
+    let preset = match name {
+        // Short frames for formants and syllables, band-limited to the speech range
+        "speech" => Overrides {
+            fft_size: Some(1024),
+            hop_size: Some(256),
+            window: Some(Window::Hann),
+            colormap: Some(Colormap::Magma),
+            db_min: Some(-100.0),
+            db_max: Some(0.0),
+            freq_min: Some(0.0),
+            freq_max: Some(FrequencyLimit::Hz(8000.0)),
+            ..Default::default()
+        },
+        // Long frames and a wide dynamic range for high-resolution music
+        "music-hires" => Overrides {
+            fft_size: Some(8192),
+            hop_size: Some(2048),
+            window: Some(Window::BlackmanHarris),
+            colormap: Some(Colormap::Turbo),
+            db_min: Some(-140.0),
+            db_max: Some(0.0),
+            freq_min: Some(0.0),
+            freq_max: Some(FrequencyLimit::Nyquist),
+            ..Default::default()
+        },
+        // Fine time resolution for fast chirps, above most low-frequency noise
+        "birdsong" => Overrides {
+            fft_size: Some(512),
+            hop_size: Some(128),
+            window: Some(Window::Hann),
+            colormap: Some(Colormap::Inferno),
+            db_min: Some(-90.0),
+            db_max: Some(0.0),
+            freq_min: Some(1000.0),
+            freq_max: Some(FrequencyLimit::Hz(12000.0)),
+            ..Default::default()
+        },
+        _ => return None,
+    };
+    Some(preset)
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("AudioSpectrogram").join("config.toml"))
+}
+
+// Config files in increasing priority: user-level, then project-local (or an explicit file)
+fn config_files(explicit: Option<&str>) -> Result<Vec<ConfigFile>, Box<dyn std::error::Error>> {
+    if let Some(path) = explicit {
+        return Ok(vec![ConfigFile::load(Path::new(path))?]);
+    }
+
+    let mut files = Vec::new();
+    let candidates = [user_config_path(), Some(PathBuf::from(PROJECT_CONFIG_FILE))];
+    for path in candidates.into_iter().flatten() {
+        if path.is_file() {
+            files.push(ConfigFile::load(&path)?);
+        }
+    }
+    Ok(files)
+}
+
+// Resolve settings: built-in defaults < config defaults < preset < command line
+pub fn resolve_settings(
+    config_path: Option<&str>,
+    preset: Option<&str>,
+    cli: &Overrides,
+) -> Result<Settings, Box<dyn std::error::Error>> {
+    let files = config_files(config_path)?;
+    let mut settings = Settings::default();
+
+    for file in &files {
+        file.defaults.apply(&mut settings);
+    }
+
+    if let Some(name) = preset {
+        // Presets from config files take precedence over built-in presets of the same name
+        let preset = files
+            .iter()
+            .rev()
+            .find_map(|file| file.presets.get(name).cloned())
+            .or_else(|| builtin_preset(name))
+            .ok_or_else(|| format!("Unknown preset: {}", name))?;
+        preset.apply(&mut settings);
+    }
+
+    cli.apply(&mut settings);
+
+    if settings.fft_size < 2 || settings.hop_size == 0 {
+        return Err("FFT size must be at least 2 and hop size must be positive".into());
+    }
+    if !settings.db_min.is_finite()
+        || !settings.db_max.is_finite()
+        || settings.db_min >= settings.db_max
+    {
+        return Err("dB range minimum must be below the maximum".into());
+    }
+    if !settings.freq_min.is_finite() || settings.freq_min < 0.0 {
+        return Err("Frequency range minimum must be zero or above".into());
+    }
+    if let Some(freq_max) = settings.freq_max
+        && (!freq_max.is_finite() || settings.freq_min >= freq_max)
+    {
+        return Err("Frequency range minimum must be below the maximum".into());
+    }
+    if !settings.font_size.is_finite() || settings.font_size <= 0.0 {
+        return Err("Font size must be positive".into());
+    }
+    if settings.waveform_height < 2 || settings.loudness_height < 2 || settings.feature_height < 2 {
+        return Err("Panel heights must be at least 2 pixels".into());
+    }
+    if settings.spectrum_width < 2 {
+        return Err("Spectrum panel width must be at least 2 pixels".into());
+    }
+    if !settings.pitch_min.is_finite()
+        || !settings.pitch_max.is_finite()
+        || settings.pitch_min <= 0.0
+        || settings.pitch_min >= settings.pitch_max
+    {
+        return Err("Pitch range minimum must be positive and below the maximum".into());
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Resolve against a config file with the given contents, written under a per-test name
+    fn resolve_with(
+        name: &str,
+        config: &str,
+        preset: Option<&str>,
+        cli: &Overrides,
+    ) -> Result<Settings, Box<dyn std::error::Error>> {
+        let path = std::env::temp_dir().join(format!(
+            "audio-spectrogram-{}-{}.toml",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, config).unwrap();
+        let settings = resolve_settings(path.to_str(), preset, cli);
+        std::fs::remove_file(&path).unwrap();
+        settings
+    }
+
+    #[test]
+    fn each_layer_overrides_the_ones_below() {
+        let config = r#"
+            [defaults]
+            db_min = -100.0
+            db_max = -10.0
+            freq_min = 20.0
+
+            [presets.voice]
+            db_min = -90.0
+            freq_min = 50.0
+        "#;
+        let cli = Overrides {
+            freq_min: Some(100.0),
+            ..Default::default()
+        };
+        let settings = resolve_with("layers", config, Some("voice"), &cli).unwrap();
+        assert_eq!(settings.fft_size, Settings::default().fft_size);
+        assert_eq!(settings.db_max, -10.0);
+        assert_eq!(settings.db_min, -90.0);
+        assert_eq!(settings.freq_min, 100.0);
+    }
+
+    #[test]
+    fn config_presets_shadow_builtin_ones() {
+        let config = "[presets.speech]\nfft_size = 512\n";
+        let settings = resolve_with("shadow", config, Some("speech"), &Overrides::default());
+        let settings = settings.unwrap();
+        assert_eq!(settings.fft_size, 512);
+        // Nothing of the built-in preset applies
+        assert_eq!(settings.freq_max, None);
+    }
+
+    #[test]
+    fn fft_size_resets_the_hop_size_unless_given() {
+        let config = "[defaults]\nfft_size = 1024\nhop_size = 100\n";
+        let resolve = |cli: Overrides| resolve_with("hop", config, None, &cli).unwrap();
+
+        let settings = resolve(Overrides::default());
+        assert_eq!((settings.fft_size, settings.hop_size), (1024, 100));
+
+        let settings = resolve(Overrides {
+            fft_size: Some(2048),
+            ..Default::default()
+        });
+        assert_eq!((settings.fft_size, settings.hop_size), (2048, 1024));
+
+        let settings = resolve(Overrides {
+            fft_size: Some(2048),
+            hop_size: Some(256),
+            ..Default::default()
+        });
+        assert_eq!((settings.fft_size, settings.hop_size), (2048, 256));
+
+        let settings = resolve(Overrides {
+            hop_size: Some(256),
+            ..Default::default()
+        });
+        assert_eq!((settings.fft_size, settings.hop_size), (1024, 256));
+    }
+
+    #[test]
+    fn unknown_preset_is_an_error() {
+        let error = resolve_with("unknown", "", Some("nope"), &Overrides::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown preset: nope");
+    }
+}
@@ -0,0 +1,332 @@
+use hound::{SampleFormat, WavReader};
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, CodecParameters, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::{MetadataOptions, Value};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::audio::{ChannelRole, DecodedAudio};
+use crate::progress::{Progress, Stage};
+
+// Interleaved samples decoded between progress updates
+pub(crate) const PROGRESS_BLOCK: usize = 1 << 16;
+
+// Input path that selects standard input
+pub const STDIN_PATH: &str = "-";
+
+// Decode a WAV file with hound, or any other supported format (and WAV that hound rejects) with
+// symphonia. `track` selects an audio track as described for `select_track`.
+pub fn read_audio(
+    path: &str,
+    track: Option<&str>,
+    progress: &dyn Progress,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    // First try to read as WAV using hound for backward compatibility
+    if track.is_none() && path.to_lowercase().ends_with(".wav") {
+        return match read_wav_samples(path, progress) {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                log::warn!("WAV decoding failed, trying generic decoder: {}", e);
+                read_generic_audio(path, None, progress)
+            }
+        };
+    }
+
+    // For other formats, use symphonia
+    read_generic_audio(path, track, progress)
+}
+
+pub fn probe_input(path: &str) -> Result<ProbeResult, Box<dyn std::error::Error>> {
+    // Create a media source from stdin or the file
+    let source: Box<dyn MediaSource> = if path == STDIN_PATH {
+        Box::new(ReadOnlySource::new(std::io::stdin()))
+    } else {
+        Box::new(File::open(path)?)
+    };
+    probe_source(path, source)
+}
+
+// Probe an already opened source, with the path only used for its extension hint
+pub fn probe_source(
+    path: &str,
+    source: Box<dyn MediaSource>,
+) -> Result<ProbeResult, Box<dyn std::error::Error>> {
+    let media_source = MediaSourceStream::new(source, Default::default());
+
+    // Create a hint to help the format registry guess what format reader is appropriate.
+    // Stdin has no extension, so the format is probed from the content alone.
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(path).extension() {
+        hint.with_extension(extension.to_str().unwrap_or(""));
+    }
+
+    // Enable gapless playback so encoder delay and padding are trimmed from the decoded audio
+    let format_opts = FormatOptions {
+        enable_gapless: true,
+        ..Default::default()
+    };
+    let metadata_opts: MetadataOptions = Default::default();
+
+    // Probe the media source to determine the format
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        media_source,
+        &format_opts,
+        &metadata_opts,
+    )?;
+
+    Ok(probed)
+}
+
+pub fn open_format(path: &str) -> Result<Box<dyn FormatReader>, Box<dyn std::error::Error>> {
+    Ok(probe_input(path)?.format)
+}
+
+fn read_generic_audio(
+    path: &str,
+    track: Option<&str>,
+    progress: &dyn Progress,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    let mut format = open_format(path)?;
+
+    // MP4/M4A readers do not provide trim information, so look for iTunes gapless metadata
+    let itunes_gapless = read_itunes_gapless_info(format.as_mut());
+
+    // Find audio track
+    let track = select_track(format.tracks(), track)?;
+    let track_id = track.id;
+    let total_frames = track.codec_params.n_frames.unwrap_or(0);
+
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+
+    // Create a decoder for the track
+    let mut decoder = match symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+    {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            log::error!("Failed to create decoder: {}", e);
+            log::error!("Please ensure the correct codec features are enabled.");
+            return Err(e.into());
+        }
+    };
+
+    let mut channels: Vec<Vec<f32>> = Vec::new();
+    let mut roles = Vec::new();
+    let mut trimmed_by_reader = false;
+
+    // Decode the audio packets
+    while let Ok(packet) = format.next_packet() {
+        // Skip packets belonging to other tracks
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        // Packet timestamps are in frames for audio tracks
+        progress.update(Stage::Decode, packet.ts() + packet.dur(), total_frames);
+
+        if packet.trim_start() > 0 || packet.trim_end() > 0 {
+            trimmed_by_reader = true;
+        }
+
+        // Decode the packet into audio samples
+        let decoded = decoder.decode(&packet)?;
+
+        // Get the audio buffer specification
+        let spec = *decoded.spec();
+        let num_channels = spec.channels.count();
+
+        // Create the sample buffer
+        let mut sample_buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+
+        // Copy the decoded audio samples into the sample buffer
+        sample_buf.copy_interleaved_ref(decoded);
+
+        let samples = sample_buf.samples();
+
+        // The layout of the first decoded packet applies to the whole track
+        if channels.is_empty() {
+            channels = vec![Vec::new(); num_channels];
+            roles = ChannelRole::from_layout(spec.channels);
+        }
+
+        // Distribute interleaved samples to channels
+        for chunk in samples.chunks_exact(num_channels) {
+            for (channel, &sample) in channels.iter_mut().zip(chunk) {
+                channel.push(sample);
+            }
+        }
+    }
+
+    // Apply the iTunes trim ourselves only when the reader did not already trim the packets
+    if let Some(gapless) = itunes_gapless.filter(|_| !trimmed_by_reader) {
+        let len = channels.first().map_or(0, Vec::len);
+        let start = (gapless.delay as usize).min(len);
+        let end = match gapless.num_frames {
+            Some(num_frames) => start.saturating_add(num_frames as usize),
+            None => len.saturating_sub(gapless.padding as usize),
+        }
+        .clamp(start, len);
+        for channel in &mut channels {
+            channel.truncate(end);
+            channel.drain(..start);
+        }
+    }
+
+    Ok(DecodedAudio {
+        channels,
+        roles,
+        sample_rate,
+    })
+}
+
+pub fn audio_tracks(tracks: &[Track]) -> Vec<&Track> {
+    tracks
+        .iter()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .collect()
+}
+
+// Select a track by index among audio tracks, by "id:<track id>" or by language code
+pub fn select_track<'a>(
+    tracks: &'a [Track],
+    selector: Option<&str>,
+) -> Result<&'a Track, Box<dyn std::error::Error>> {
+    let audio = audio_tracks(tracks);
+    let Some(selector) = selector else {
+        return audio.first().copied().ok_or_else(|| {
+            "No valid audio track found. If this is an OGG file, it might contain cover art.".into()
+        });
+    };
+
+    let found = if let Some(id) = selector.strip_prefix("id:") {
+        let id: u32 = id
+            .parse()
+            .map_err(|_| format!("Invalid track id: {}", id))?;
+        audio.into_iter().find(|track| track.id == id)
+    } else if let Ok(index) = selector.parse::<usize>() {
+        audio.get(index).copied()
+    } else {
+        audio.into_iter().find(|track| {
+            track
+                .language
+                .as_deref()
+                .is_some_and(|language| language.eq_ignore_ascii_case(selector))
+        })
+    };
+
+    found.ok_or_else(|| {
+        format!(
+            "No audio track matches \"{}\". Use --list-tracks to see available tracks.",
+            selector
+        )
+        .into()
+    })
+}
+
+// Track duration in seconds, if the container declares the frame count
+pub fn track_duration(params: &CodecParameters) -> Option<f64> {
+    let time = params.time_base?.calc_time(params.n_frames?);
+    Some(time.seconds as f64 + time.frac)
+}
+
+// Encoder delay and padding declared by an iTunSMPB tag
+struct GaplessInfo {
+    delay: u32,
+    padding: u32,
+    num_frames: Option<u64>,
+}
+
+fn read_itunes_gapless_info(format: &mut dyn FormatReader) -> Option<GaplessInfo> {
+    let metadata = format.metadata();
+    let revision = metadata.current()?;
+    let tag = revision
+        .tags()
+        .iter()
+        .find(|tag| tag.key.to_lowercase().ends_with("itunsmpb"))?;
+
+    let text = match &tag.value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+
+    // Layout: " 00000000 <delay> <padding> <original frame count> ..." in hexadecimal
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let delay = u32::from_str_radix(fields.get(1)?, 16).ok()?;
+    let padding = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+    let num_frames = fields
+        .get(3)
+        .and_then(|field| u64::from_str_radix(field, 16).ok())
+        .filter(|&frames| frames > 0);
+
+    Some(GaplessInfo {
+        delay,
+        padding,
+        num_frames,
+    })
+}
+
+fn read_wav_samples(path: &str, progress: &dyn Progress) -> Result<DecodedAudio, hound::Error> {
+    let reader = WavReader::open(path)?;
+    let sample_rate = reader.spec().sample_rate;
+    let sample_format = reader.spec().sample_format;
+    let channels = reader.spec().channels as usize;
+    let reader_bits = reader.spec().bits_per_sample;
+    let total_frames = reader.duration() as u64;
+
+    // Report progress once per block of interleaved samples
+    let report = |i: usize| {
+        if i.is_multiple_of(PROGRESS_BLOCK) {
+            progress.update(Stage::Decode, (i / channels) as u64, total_frames);
+        }
+    };
+
+    let mut channel_samples: Vec<Vec<f32>> = vec![Vec::new(); channels];
+
+    // First collect all channel data
+    match sample_format {
+        SampleFormat::Int => {
+            let samples: Vec<i32> = reader
+                .into_samples::<i32>()
+                .enumerate()
+                .map(|(i, s)| {
+                    report(i);
+                    s.unwrap()
+                })
+                .collect();
+            // Scale to full range of the sample format
+            let full_scale = (1u64 << (reader_bits - 1)) as f32;
+
+            // Distribute samples to channels
+            for (i, &sample) in samples.iter().enumerate() {
+                let channel = i % channels;
+                channel_samples[channel].push(sample as f32 / full_scale);
+            }
+        }
+        SampleFormat::Float => {
+            let samples: Vec<f32> = reader
+                .into_samples::<f32>()
+                .enumerate()
+                .map(|(i, s)| {
+                    report(i);
+                    s.unwrap()
+                })
+                .collect();
+
+            // Distribute samples to channels
+            for (i, &sample) in samples.iter().enumerate() {
+                let channel = i % channels;
+                channel_samples[channel].push(sample);
+            }
+        }
+    }
+
+    Ok(DecodedAudio {
+        channels: channel_samples,
+        roles: ChannelRole::defaults(channels),
+        sample_rate,
+    })
+}
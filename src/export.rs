@@ -0,0 +1,80 @@
+use serde::Serialize;
+use std::io::Write;
+
+use crate::cli::ExportFormat;
+use crate::config::Settings;
+use crate::{magnitude_to_db, open_output};
+
+#[derive(Serialize)]
+struct SpectrogramData<'a> {
+    sample_rate: u32,
+    fft_size: usize,
+    hop_size: usize,
+    times: Vec<f64>,
+    frequencies: Vec<f64>,
+    /// dB magnitudes indexed as [frame][bin]
+    magnitudes_db: &'a [Vec<f32>],
+}
+
+// Write per-frame dB magnitudes of the given bins, one row per frame with its start time
+pub fn write_spectrogram(
+    path: &str,
+    format: ExportFormat,
+    frames: &[Vec<f32>],
+    bins: std::ops::Range<usize>,
+    sample_rate: u32,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fft_size = settings.fft_size;
+    let hop_size = settings.hop_size;
+
+    let times: Vec<f64> = (0..frames.len())
+        .map(|i| (i * hop_size) as f64 / sample_rate as f64)
+        .collect();
+    let frequencies: Vec<f64> = bins
+        .clone()
+        .map(|bin| bin as f64 * sample_rate as f64 / fft_size as f64)
+        .collect();
+    let frames_db: Vec<Vec<f32>> = frames
+        .iter()
+        .map(|frame| {
+            frame[bins.clone()]
+                .iter()
+                .map(|&m| magnitude_to_db(m))
+                .collect()
+        })
+        .collect();
+
+    let mut writer = open_output(path)?;
+    match format {
+        ExportFormat::Csv => {
+            write!(writer, "time_s")?;
+            for frequency in &frequencies {
+                write!(writer, ",{:.2}", frequency)?;
+            }
+            writeln!(writer)?;
+
+            for (time, frame) in times.iter().zip(&frames_db) {
+                write!(writer, "{:.6}", time)?;
+                for db in frame {
+                    write!(writer, ",{:.2}", db)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        ExportFormat::Json => {
+            let data = SpectrogramData {
+                sample_rate,
+                fft_size,
+                hop_size,
+                times,
+                frequencies,
+                magnitudes_db: &frames_db,
+            };
+            serde_json::to_writer(&mut writer, &data)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use clap::ValueEnum;
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use crate::axis::{self, AxisTicks, FrequencyUnit};
+use crate::cli::ExportFormat;
+use crate::config::Settings;
+use crate::layout::Rect;
+use crate::open_output;
+use crate::stft::amplitude_scale;
+use crate::theme::Palette;
+
+// Share of the spectral energy below the rolloff frequency
+const ROLLOFF_FRACTION: f64 = 0.85;
+// Power floor for the spectral flatness relative to a full-scale sine, keeping silent bins out
+// of the logarithm; frames with less power in total have a flatness of 0
+const FLATNESS_FLOOR: f64 = 1e-10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Feature {
+    Centroid,
+    Bandwidth,
+    Rolloff,
+    Flatness,
+    Flux,
+    Zcr,
+    Rms,
+}
+
+impl Feature {
+    fn value(self, features: &FrameFeatures) -> f32 {
+        match self {
+            Feature::Centroid => features.centroid_hz,
+            Feature::Bandwidth => features.bandwidth_hz,
+            Feature::Rolloff => features.rolloff_hz,
+            Feature::Flatness => features.flatness,
+            Feature::Flux => features.flux,
+            Feature::Zcr => features.zero_crossing_rate,
+            Feature::Rms => features.rms,
+        }
+    }
+
+    // Scale of the feature panel from zero to `max`: frequency ticks for the features in Hz,
+    // otherwise the bottom, middle and top values
+    pub fn panel_ticks(
+        self,
+        max: f32,
+        unit: FrequencyUnit,
+        panel_height: f32,
+        measure: &dyn Fn(&str) -> f32,
+    ) -> AxisTicks {
+        match self {
+            Feature::Centroid | Feature::Bandwidth | Feature::Rolloff => {
+                axis::frequency_ticks((0.0, max), unit, panel_height, false, measure)
+            }
+            _ => AxisTicks {
+                major: [0.0, 0.5, 1.0]
+                    .iter()
+                    .map(|&fraction| (fraction, format!("{:.2}", fraction * max)))
+                    .collect(),
+                minor: Vec::new(),
+            },
+        }
+    }
+}
+
+// Descriptors of one STFT frame; spectral values cover all bins, not just the plotted range
+#[derive(Clone, Copy, Serialize)]
+pub struct FrameFeatures {
+    /// Start of the frame, as in the spectrogram export
+    pub time_s: f64,
+    /// Magnitude-weighted mean frequency
+    pub centroid_hz: f32,
+    /// Magnitude-weighted standard deviation around the centroid
+    pub bandwidth_hz: f32,
+    /// Frequency below which 85% of the energy lies
+    pub rolloff_hz: f32,
+    /// Geometric over arithmetic mean of the power spectrum, 1 for white noise
+    pub flatness: f32,
+    /// Euclidean norm of the magnitude increases since the previous frame
+    pub flux: f32,
+    /// Sign changes per sample
+    pub zero_crossing_rate: f32,
+    /// Root mean square of the frame's samples, 1 for a full-scale square wave
+    pub rms: f32,
+}
+
+// Features of every STFT frame, with frames computed by `stft_magnitudes` from `samples`
+pub fn extract(
+    samples: &[f32],
+    frames: &[Vec<f32>],
+    sample_rate: u32,
+    settings: &Settings,
+) -> Vec<FrameFeatures> {
+    let bin_width = sample_rate as f64 / settings.fft_size as f64;
+    let scale = amplitude_scale(&settings.window.coefficients(settings.fft_size));
+
+    let mut previous: Option<&Vec<f32>> = None;
+    frames
+        .iter()
+        .enumerate()
+        .map(|(i, spectrum)| {
+            let start = i * settings.hop_size;
+            let chunk = &samples[start..(start + settings.fft_size).min(samples.len())];
+
+            let magnitude_sum: f64 = spectrum.iter().map(|&m| m as f64).sum();
+            let weighted = |f: &dyn Fn(f64) -> f64| {
+                let sum: f64 = spectrum
+                    .iter()
+                    .enumerate()
+                    .map(|(bin, &m)| m as f64 * f(bin as f64 * bin_width))
+                    .sum();
+                if magnitude_sum > 0.0 {
+                    sum / magnitude_sum
+                } else {
+                    0.0
+                }
+            };
+            let centroid = weighted(&|frequency| frequency);
+            let bandwidth = weighted(&|frequency| (frequency - centroid).powi(2)).sqrt();
+
+            let power: Vec<f64> = spectrum.iter().map(|&m| (m as f64).powi(2)).collect();
+            let total: f64 = power.iter().sum();
+            let mut cumulative = 0.0;
+            let rolloff_bin = power
+                .iter()
+                .position(|&p| {
+                    cumulative += p;
+                    cumulative >= ROLLOFF_FRACTION * total
+                })
+                .unwrap_or(0);
+
+            let scaled = power.iter().map(|&p| p * (scale as f64).powi(2));
+            let flatness = if scaled.clone().sum::<f64>() < FLATNESS_FLOOR {
+                0.0
+            } else {
+                let floored = scaled.map(|p| p.max(FLATNESS_FLOOR));
+                let log_mean = floored.clone().map(f64::ln).sum::<f64>() / power.len() as f64;
+                let mean = floored.sum::<f64>() / power.len() as f64;
+                log_mean.exp() / mean
+            };
+
+            let flux = previous.map_or(0.0, |previous| {
+                spectrum
+                    .iter()
+                    .zip(previous)
+                    .map(|(&m, &p)| ((m - p) * scale).max(0.0).powi(2))
+                    .sum::<f32>()
+                    .sqrt()
+            });
+            previous = Some(spectrum);
+
+            let crossings = chunk
+                .windows(2)
+                .filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0))
+                .count();
+            let power_mean = chunk.iter().map(|&s| s * s).sum::<f32>() / chunk.len().max(1) as f32;
+
+            FrameFeatures {
+                time_s: start as f64 / sample_rate as f64,
+                centroid_hz: centroid as f32,
+                bandwidth_hz: bandwidth as f32,
+                rolloff_hz: (rolloff_bin as f64 * bin_width) as f32,
+                flatness: flatness as f32,
+                flux,
+                zero_crossing_rate: crossings as f32 / chunk.len().saturating_sub(1).max(1) as f32,
+                rms: power_mean.sqrt(),
+            }
+        })
+        .collect()
+}
+
+// Top of the feature panel's scale: the largest value, or 1 when the feature is zero throughout
+pub fn panel_max(features: &[FrameFeatures], feature: Feature) -> f32 {
+    let max = features
+        .iter()
+        .map(|f| feature.value(f))
+        .fold(0.0, f32::max);
+    if max > 0.0 { max } else { 1.0 }
+}
+
+// Plot one feature over the panel, one column per frame, from zero at the bottom to `max`
+pub fn draw_panel(
+    img: &mut RgbaImage,
+    panel: &Rect,
+    features: &[FrameFeatures],
+    feature: Feature,
+    max: f32,
+    palette: &Palette,
+) {
+    let y_of = |value: f32| {
+        let normalized = (value / max).clamp(0.0, 1.0);
+        panel.bottom() as f32 - normalized * (panel.height as f32 - 1.0)
+    };
+    for (x, pair) in features.windows(2).enumerate() {
+        let x = (panel.x + x as u32) as f32;
+        draw_line_segment_mut(
+            img,
+            (x, y_of(feature.value(&pair[0]))),
+            (x + 1.0, y_of(feature.value(&pair[1]))),
+            palette.feature,
+        );
+    }
+
+    crate::draw_outline(img, panel, palette.axes);
+}
+
+// Trace the spectral centroid and rolloff over the plot, one column per frame
+pub fn draw_overlay(
+    img: &mut RgbaImage,
+    plot: &Rect,
+    features: &[FrameFeatures],
+    (min_freq, max_freq): (f32, f32),
+    palette: &Palette,
+) {
+    let curves = [
+        (Feature::Rolloff, palette.feature_rolloff),
+        (Feature::Centroid, palette.feature_centroid),
+    ];
+    for (feature, color) in curves {
+        let y_of = |features: &FrameFeatures| {
+            let fraction = (feature.value(features) - min_freq) / (max_freq - min_freq);
+            (0.0..=1.0)
+                .contains(&fraction)
+                .then(|| crate::frequency_y(plot, fraction))
+        };
+        for (x, pair) in features.windows(2).enumerate() {
+            if let (Some(from), Some(to)) = (y_of(&pair[0]), y_of(&pair[1])) {
+                let x = (plot.x + x as u32) as f32;
+                draw_line_segment_mut(img, (x, from), (x + 1.0, to), color);
+            }
+        }
+    }
+}
+
+// Write one row or object per frame
+pub fn write_features(
+    path: &str,
+    format: ExportFormat,
+    features: &[FrameFeatures],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = open_output(path)?;
+    match format {
+        ExportFormat::Csv => {
+            writeln!(
+                writer,
+                "time_s,centroid_hz,bandwidth_hz,rolloff_hz,flatness,flux,zero_crossing_rate,rms"
+            )?;
+            for f in features {
+                writeln!(
+                    writer,
+                    "{:.6},{:.2},{:.2},{:.2},{:.6},{:.6},{:.6},{:.6}",
+                    f.time_s,
+                    f.centroid_hz,
+                    f.bandwidth_hz,
+                    f.rolloff_hz,
+                    f.flatness,
+                    f.flux,
+                    f.zero_crossing_rate,
+                    f.rms
+                )?;
+            }
+        }
+        ExportFormat::Json => serde_json::to_writer(&mut writer, features)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{sine, white_noise};
+
+    fn features_of(samples: &[f32]) -> Vec<FrameFeatures> {
+        let settings = Settings::default();
+        let frames = crate::stft_magnitudes(samples, &settings, &|_, _, _| {});
+        extract(samples, &frames, 44100, &settings)
+    }
+
+    #[test]
+    fn silence_has_zero_flatness() {
+        let features = features_of(&vec![0.0; 44100]);
+        assert!(!features.is_empty());
+        for frame in &features {
+            assert_eq!(frame.flatness, 0.0);
+            assert_eq!(frame.rms, 0.0);
+        }
+    }
+
+    #[test]
+    fn white_noise_is_flat() {
+        // The power of windowed white noise per bin is exponentially distributed, for a
+        // flatness of about exp(-0.577) = 0.56
+        for frame in &features_of(&white_noise(44100)) {
+            assert!((0.45..0.7).contains(&frame.flatness), "{}", frame.flatness);
+        }
+    }
+
+    #[test]
+    fn sine_is_tonal_with_its_centroid_at_the_tone() {
+        for frame in &features_of(&sine(1000.0, 0.5, 1.0, 44100)) {
+            assert!(frame.flatness < 0.01, "flatness {}", frame.flatness);
+            assert!(
+                (frame.centroid_hz - 1000.0).abs() < 50.0,
+                "{}",
+                frame.centroid_hz
+            );
+            assert!(
+                (frame.rms - 0.5 / 2f32.sqrt()).abs() < 0.01,
+                "rms {}",
+                frame.rms
+            );
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::io::{MediaSource, ReadOnlySource};
+use symphonia::core::meta::MetadataRevision;
+
+use crate::{STDIN_PATH, probe_source, track_duration};
+
+#[derive(Serialize)]
+struct StreamInfo {
+    path: String,
+    container: String,
+    tracks: Vec<TrackInfo>,
+    tags: Vec<TagInfo>,
+    artwork: Vec<ArtworkInfo>,
+}
+
+#[derive(Serialize)]
+struct TrackInfo {
+    id: u32,
+    codec: String,
+    codec_description: String,
+    sample_rate: Option<u32>,
+    bits_per_sample: Option<u32>,
+    channels: Option<usize>,
+    channel_layout: Option<String>,
+    duration_seconds: Option<f64>,
+    frames: Option<u64>,
+    language: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TagInfo {
+    key: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct ArtworkInfo {
+    media_type: String,
+    usage: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    size_bytes: usize,
+}
+
+pub fn print_info(path: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let info = collect_info(path)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print_human(&info);
+    }
+
+    Ok(())
+}
+
+fn collect_info(path: &str) -> Result<StreamInfo, Box<dyn std::error::Error>> {
+    let mut header = Vec::new();
+    let source = open_sniffed(path, &mut header)?;
+    let mut probed = probe_source(path, source)?;
+    let codecs = symphonia::default::get_codecs();
+
+    let tracks = probed
+        .format
+        .tracks()
+        .iter()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .map(|track| {
+            let params = &track.codec_params;
+            let descriptor = codecs.get_codec(params.codec);
+            TrackInfo {
+                id: track.id,
+                codec: descriptor
+                    .map(|d| d.short_name.to_string())
+                    .unwrap_or_else(|| format!("{}", params.codec)),
+                codec_description: descriptor
+                    .map(|d| d.long_name.to_string())
+                    .unwrap_or_default(),
+                sample_rate: params.sample_rate,
+                bits_per_sample: params.bits_per_sample.or(params.bits_per_coded_sample),
+                channels: params.channels.map(|channels| channels.count()),
+                channel_layout: params
+                    .channels
+                    .map(|channels| format!("{:?}", channels))
+                    .or_else(|| params.channel_layout.map(|layout| format!("{:?}", layout))),
+                duration_seconds: track_duration(params),
+                frames: params.n_frames,
+                language: track.language.clone(),
+            }
+        })
+        .collect();
+
+    // Tags and artwork may live outside the container (e.g. ID3v2) or inside it
+    let mut tags = Vec::new();
+    let mut artwork = Vec::new();
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current()
+    {
+        collect_metadata(revision, &mut tags, &mut artwork);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        collect_metadata(revision, &mut tags, &mut artwork);
+    }
+
+    Ok(StreamInfo {
+        path: path.to_string(),
+        container: detect_container(&header).to_string(),
+        tracks,
+        tags,
+        artwork,
+    })
+}
+
+fn collect_metadata(
+    revision: &MetadataRevision,
+    tags: &mut Vec<TagInfo>,
+    artwork: &mut Vec<ArtworkInfo>,
+) {
+    for tag in revision.tags() {
+        let key = match tag.std_key {
+            Some(std_key) => format!("{:?}", std_key),
+            None => tag.key.clone(),
+        };
+        tags.push(TagInfo {
+            key,
+            value: tag.value.to_string(),
+        });
+    }
+
+    for visual in revision.visuals() {
+        artwork.push(ArtworkInfo {
+            media_type: visual.media_type.clone(),
+            usage: visual.usage.map(|usage| format!("{:?}", usage)),
+            width: visual.dimensions.map(|size| size.width),
+            height: visual.dimensions.map(|size| size.height),
+            size_bytes: visual.data.len(),
+        });
+    }
+}
+
+// Open the input after reading its magic bytes into `header`, returning a source that still
+// starts at the beginning: a file is rewound, while stdin cannot be reopened, so its bytes are
+// replayed
+fn open_sniffed(
+    path: &str,
+    header: &mut Vec<u8>,
+) -> Result<Box<dyn MediaSource>, Box<dyn std::error::Error>> {
+    if path == STDIN_PATH {
+        let mut stdin = std::io::stdin();
+        (&mut stdin).take(12).read_to_end(header)?;
+        let replay = Cursor::new(header.clone()).chain(stdin);
+        Ok(Box::new(ReadOnlySource::new(replay)))
+    } else {
+        let mut file = File::open(path)?;
+        (&mut file).take(12).read_to_end(header)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Box::new(file))
+    }
+}
+
+// Identify the container from its magic bytes, since symphonia does not expose the reader name
+fn detect_container(header: &[u8]) -> &'static str {
+    if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WAVE") {
+        "WAV"
+    } else if header.starts_with(b"fLaC") {
+        "FLAC"
+    } else if header.starts_with(b"OggS") {
+        "Ogg"
+    } else if header.get(4..8) == Some(b"ftyp") {
+        "MP4"
+    } else if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        "Matroska/WebM"
+    } else if header.starts_with(b"ID3") {
+        "MP3 (ID3v2)"
+    } else if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xF6 == 0xF0 {
+        "ADTS"
+    } else if header.len() >= 2 && header[0] == 0xFF && header[1] & 0xE0 == 0xE0 {
+        "MPEG audio"
+    } else {
+        "unknown"
+    }
+}
+
+fn print_human(info: &StreamInfo) {
+    println!("File      : {}", info.path);
+    println!("Container : {}", info.container);
+
+    for (index, track) in info.tracks.iter().enumerate() {
+        println!("Track [{}] (id={})", index, track.id);
+        println!(
+            "  Codec       : {} ({})",
+            track.codec, track.codec_description
+        );
+        println!("  Sample rate : {}", optional(track.sample_rate, " Hz"));
+        println!(
+            "  Bit depth   : {}",
+            optional(track.bits_per_sample, " bits")
+        );
+        println!("  Channels    : {}", optional(track.channels, ""));
+        println!(
+            "  Layout      : {}",
+            track.channel_layout.as_deref().unwrap_or("unknown")
+        );
+        println!(
+            "  Duration    : {}",
+            track
+                .duration_seconds
+                .map(|seconds| format!("{:.3}s", seconds))
+                .unwrap_or_else(|| "unknown".to_string())
+        );
+        println!("  Frames      : {}", optional(track.frames, ""));
+        println!(
+            "  Language    : {}",
+            track.language.as_deref().unwrap_or("und")
+        );
+    }
+
+    if !info.tags.is_empty() {
+        println!("Tags");
+        for tag in &info.tags {
+            println!("  {:<20}: {}", tag.key, tag.value);
+        }
+    }
+
+    if info.artwork.is_empty() {
+        println!("Artwork   : none");
+    } else {
+        println!("Artwork");
+        for visual in &info.artwork {
+            let dimensions = match (visual.width, visual.height) {
+                (Some(width), Some(height)) => format!("{}x{}", width, height),
+                _ => "unknown size".to_string(),
+            };
+            println!(
+                "  {} {} {} ({} bytes)",
+                visual.usage.as_deref().unwrap_or("Other"),
+                visual.media_type,
+                dimensions,
+                visual.size_bytes
+            );
+        }
+    }
+}
+
+fn optional<T: std::fmt::Display>(value: Option<T>, unit: &str) -> String {
+    value
+        .map(|value| format!("{}{}", value, unit))
+        .unwrap_or_else(|| "unknown".to_string())
+}
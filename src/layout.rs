@@ -0,0 +1,274 @@
+use clap::ValueEnum;
+use imageproc::drawing::text_size;
+use rusttype::{Font, Scale, point};
+use serde::Deserialize;
+
+// Length of major tick marks; minor ticks are drawn at MINOR_TICK_LENGTH
+pub const TICK_LENGTH: f32 = 5.0;
+pub const MINOR_TICK_LENGTH: f32 = 3.0;
+
+pub const COLORBAR_WIDTH: u32 = 30;
+
+// Decorations around the plot that can be hidden individually
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Element {
+    FrequencyAxis,
+    TimeAxis,
+    Colorbar,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+}
+
+// A font at a fixed size, used to both measure and draw labels
+pub struct TextStyle<'a> {
+    pub font: &'a Font<'a>,
+    pub scale: Scale,
+}
+
+impl<'a> TextStyle<'a> {
+    pub fn new(font: &'a Font<'a>, size: f32) -> Self {
+        TextStyle {
+            font,
+            scale: Scale::uniform(size),
+        }
+    }
+
+    pub fn width(&self, text: &str) -> f32 {
+        text_size(self.scale, self.font, text).0 as f32
+    }
+
+    // Height of a line of arbitrary text, from the top of its ascenders to its descenders
+    pub fn line_height(&self) -> f32 {
+        let metrics = self.font.v_metrics(self.scale);
+        metrics.ascent - metrics.descent
+    }
+
+    // Height of digits, which make up nearly every label
+    pub fn digit_height(&self) -> f32 {
+        let (top, bottom) = self.digit_extent();
+        bottom - top
+    }
+
+    // Offset from a label's drawing origin to the vertical middle of its digits
+    pub fn center_offset(&self) -> f32 {
+        let (top, bottom) = self.digit_extent();
+        (top + bottom) / 2.0
+    }
+
+    // Top and bottom of "0" below the drawing origin, as laid out by draw_text_mut
+    fn digit_extent(&self) -> (f32, f32) {
+        let ascent = self.font.v_metrics(self.scale).ascent;
+        self.font
+            .layout("0", self.scale, point(0.0, ascent))
+            .find_map(|glyph| glyph.pixel_bounding_box())
+            .map(|bb| (bb.min.y as f32, bb.max.y as f32))
+            .unwrap_or((0.0, self.scale.y))
+    }
+}
+
+// User-facing layout options
+#[derive(Clone, Debug)]
+pub struct LayoutOptions {
+    pub font_size: f32,
+    /// Blank border around the whole image in pixels
+    pub margin: u32,
+    pub hidden: Vec<Element>,
+    /// Height of the waveform panel above the plot, `None` for no panel
+    pub waveform_height: Option<u32>,
+    /// Height of the loudness panel above the plot, `None` for no panel
+    pub loudness_height: Option<u32>,
+    /// Height of the feature panel above the plot, `None` for no panel
+    pub feature_height: Option<u32>,
+    /// Width of the average spectrum panel right of the plot, `None` for no panel
+    pub spectrum_width: Option<u32>,
+}
+
+impl LayoutOptions {
+    pub fn shows(&self, element: Element) -> bool {
+        !self.hidden.contains(&element)
+    }
+}
+
+// Labels the layout must leave room for, as measured widths in pixels
+pub struct LabelExtents {
+    /// Widest label on the frequency axis and the scales of the panels above the plot
+    pub frequency: f32,
+    /// How far time labels centred on their ticks reach past the left and right plot edges
+    pub time_overhang: (f32, f32),
+    /// Widest colorbar label
+    pub colorbar: f32,
+}
+
+// Positions of every element of the image
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    pub plot: Rect,
+    pub waveform: Option<Rect>,
+    pub loudness: Option<Rect>,
+    pub features: Option<Rect>,
+    pub spectrum: Option<Rect>,
+    pub frequency_axis: bool,
+    pub time_axis: bool,
+    pub colorbar: Option<Rect>,
+    /// Space between a tick mark and its label
+    pub label_gap: f32,
+}
+
+impl Layout {
+    pub fn compute(
+        plot_width: u32,
+        plot_height: u32,
+        options: &LayoutOptions,
+        labels: &LabelExtents,
+        axis_style: &TextStyle,
+    ) -> Self {
+        let frequency_axis = options.shows(Element::FrequencyAxis);
+        let time_axis = options.shows(Element::TimeAxis);
+        let show_colorbar = options.shows(Element::Colorbar);
+
+        let label_gap = (options.font_size * 0.4).round();
+        let label_height = axis_style.digit_height();
+        let margin = options.margin as f32;
+
+        // Labels centred on the top and bottom edges reach half a label beyond the plot
+        let half_label = if frequency_axis || show_colorbar {
+            label_height / 2.0
+        } else {
+            0.0
+        };
+
+        let mut left = margin;
+        if frequency_axis {
+            left += TICK_LENGTH + label_gap + labels.frequency;
+        }
+        if time_axis {
+            left = left.max(margin + labels.time_overhang.0);
+        }
+
+        // Panels stacked above the plot, top to bottom
+        let mut top = margin + half_label;
+        let mut stack = |panel_height: Option<u32>| {
+            let panel = Rect {
+                x: left.ceil() as u32,
+                y: top.ceil() as u32,
+                width: plot_width,
+                height: panel_height?,
+            };
+            // Keep the labels on the panel's bottom edge and the next top edge apart
+            top = panel.bottom() as f32 + label_height + label_gap;
+            Some(panel)
+        };
+        let waveform = stack(options.waveform_height);
+        let loudness = stack(options.loudness_height);
+        let features = stack(options.feature_height);
+
+        // The average spectrum panel has its dB labels in the same row as the time labels
+        let mut bottom = margin + half_label;
+        if time_axis || options.spectrum_width.is_some() {
+            bottom = bottom.max(margin + TICK_LENGTH + label_gap + label_height);
+        }
+
+        // Panels right of the plot, each placed after the previous one
+        let panel_gap = (options.font_size * 1.5).round();
+        let time_overhang = if time_axis {
+            labels.time_overhang.1
+        } else {
+            0.0
+        };
+        // Keep the spectrum panel's dB labels clear of the last time label
+        let spectrum_offset = panel_gap.max(time_overhang + 2.0 * label_gap);
+        let panels_end = match options.spectrum_width {
+            Some(width) => spectrum_offset + width as f32,
+            None => 0.0,
+        };
+        let colorbar_offset = panels_end + panel_gap;
+        let mut offset = time_overhang.max(panels_end);
+        if show_colorbar {
+            let colorbar_labels = TICK_LENGTH + label_gap + labels.colorbar;
+            offset = offset.max(colorbar_offset + COLORBAR_WIDTH as f32 + colorbar_labels);
+        }
+
+        let plot = Rect {
+            x: left.ceil() as u32,
+            y: top.ceil() as u32,
+            width: plot_width,
+            height: plot_height,
+        };
+        let spectrum = options.spectrum_width.map(|width| Rect {
+            x: plot.right() + spectrum_offset.ceil() as u32,
+            y: plot.y,
+            width,
+            height: plot_height,
+        });
+        let colorbar = show_colorbar.then(|| Rect {
+            x: plot.right() + colorbar_offset.ceil() as u32,
+            y: plot.y,
+            width: COLORBAR_WIDTH,
+            height: plot_height,
+        });
+
+        Layout {
+            width: plot.right() + (offset + margin).ceil() as u32,
+            height: plot.bottom() + bottom.ceil() as u32,
+            plot,
+            waveform,
+            loudness,
+            features,
+            spectrum,
+            frequency_axis,
+            time_axis,
+            colorbar,
+            label_gap,
+        }
+    }
+}
+
+// Pixel dimensions given as WIDTHxHEIGHT, e.g. "512x256"
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for ImageSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid size \"{}\", expected WIDTHxHEIGHT", s);
+        let (width, height) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(ImageSize { width, height })
+    }
+}
+
+impl TryFrom<String> for ImageSize {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
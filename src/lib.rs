@@ -0,0 +1,9 @@
+//! Decoding and STFT analysis behind the AudioSpectrogram command line tool, for use from other
+//! front ends such as GUIs. Long-running steps report through a [`progress::Progress`], which
+//! any `Fn(Stage, u64, u64)` closure implements.
+
+pub mod audio;
+pub mod decode;
+pub mod progress;
+pub mod raw;
+pub mod stft;
@@ -0,0 +1,315 @@
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+use serde::Serialize;
+
+use crate::audio::DecodedAudio;
+use crate::layout::Rect;
+use crate::stft::Window;
+use crate::theme::Palette;
+
+// Fixed analysis resolution, independent of the spectrogram settings
+const FFT_SIZE: usize = 4096;
+const BLOCK_SECONDS: f64 = 1.0;
+// Width of the moving average applied to each block's spectrum
+const SMOOTHING_HZ: f64 = 200.0;
+// Cutoffs are searched above this frequency
+const MIN_CUTOFF_HZ: f64 = 2000.0;
+// Blocks quieter than this everywhere are skipped
+const SILENCE_DBFS: f64 = -90.0;
+// The cutoff is the highest frequency this far above the block's spectral floor
+const FLOOR_MARGIN_DB: f64 = 15.0;
+// Drop across the cutoff that marks an encoder's lowpass rather than a natural roll-off
+const SHELF_DB: f64 = 30.0;
+const SHELF_NEAR_HZ: f64 = 250.0;
+const SHELF_FAR_HZ: f64 = 1000.0;
+// Cutoffs this close to Nyquist are normal anti-aliasing filters
+const FULL_BAND_FRACTION: f64 = 0.93;
+// Upsampled material keeps the lowpass of its 44.1 or 48 kHz source
+const UPSAMPLED_MIN_HZ: f64 = 20000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    /// Content reaches the Nyquist frequency, or any shelf is too rare to be an encoder's
+    FullBandwidth,
+    /// A steady shelf well below Nyquist, typical of a lossy encoder's lowpass
+    Lossy,
+    /// A shelf around 20-24 kHz in a high sample rate file
+    Upsampled,
+    /// Too little audible material to judge
+    Inconclusive,
+}
+
+impl Verdict {
+    fn description(self) -> &'static str {
+        match self {
+            Verdict::FullBandwidth => "full bandwidth, no sign of lossy encoding",
+            Verdict::Lossy => "likely transcoded from a lossy source",
+            Verdict::Upsampled => "likely upsampled from 44.1 or 48 kHz",
+            Verdict::Inconclusive => "inconclusive, too little signal",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LossyReport {
+    pub verdict: Verdict,
+    /// Median cutoff of the blocks ending in a shelf
+    pub cutoff_hz: Option<f64>,
+    /// Typical encoder bitrate for the cutoff, only given for lossy verdicts
+    pub bitrate_class: Option<&'static str>,
+    /// Share of measured blocks ending in a shelf
+    pub shelf_fraction: f64,
+    pub blocks: Vec<CutoffBlock>,
+}
+
+#[derive(Serialize)]
+pub struct CutoffBlock {
+    pub start_s: f64,
+    pub end_s: f64,
+    /// Effective bandwidth, `None` for silent blocks
+    pub cutoff_hz: Option<f64>,
+    /// Whether the spectrum drops off steeply at the cutoff
+    pub shelf: bool,
+}
+
+pub fn analyze(audio: &DecodedAudio) -> LossyReport {
+    let samples = audio.mono();
+    let sample_rate = audio.sample_rate as f64;
+    let bin_width = sample_rate / FFT_SIZE as f64;
+    let nyquist = sample_rate / 2.0;
+
+    // Scale magnitudes so a full-scale sine reads 0 dBFS
+    let window = Window::Hann.coefficients(FFT_SIZE);
+    let scale = 2.0 / window.iter().map(|&w| w as f64).sum::<f64>();
+
+    let frames_per_block =
+        ((BLOCK_SECONDS * sample_rate / FFT_SIZE as f64).round() as usize).max(1);
+    let block_samples = frames_per_block * FFT_SIZE;
+    let mut blocks = Vec::new();
+    for (index, block) in samples.chunks(block_samples).enumerate() {
+        let frames: Vec<_> = block.chunks_exact(FFT_SIZE).collect();
+        if frames.is_empty() {
+            break;
+        }
+
+        let mut power = vec![0.0f64; FFT_SIZE / 2];
+        for frame in &frames {
+            let spectrum = crate::compute_spectrum(frame, &window);
+            for (p, &magnitude) in power.iter_mut().zip(&spectrum) {
+                *p += (magnitude as f64 * scale).powi(2);
+            }
+        }
+        let db: Vec<f64> = power
+            .iter()
+            .map(|&p| 10.0 * (p / frames.len() as f64 + 1e-20).log10())
+            .collect();
+
+        let start = index * block_samples;
+        let (cutoff_hz, shelf) = match block_cutoff(&smooth(&db, bin_width), bin_width) {
+            Some((bin, shelf)) => (Some(bin as f64 * bin_width), shelf),
+            None => (None, false),
+        };
+        blocks.push(CutoffBlock {
+            start_s: start as f64 / sample_rate,
+            end_s: (start + frames.len() * FFT_SIZE) as f64 / sample_rate,
+            cutoff_hz,
+            shelf,
+        });
+    }
+
+    let measured = blocks.iter().filter(|b| b.cutoff_hz.is_some()).count();
+    let mut shelves: Vec<f64> = blocks
+        .iter()
+        .filter(|b| b.shelf)
+        .filter_map(|b| b.cutoff_hz)
+        .collect();
+    shelves.sort_by(f64::total_cmp);
+    let shelf_fraction = shelves.len() as f64 / measured.max(1) as f64;
+    let cutoff_hz = shelves.get(shelves.len() / 2).copied();
+
+    let verdict = match cutoff_hz {
+        _ if measured == 0 => Verdict::Inconclusive,
+        Some(cutoff) if shelf_fraction >= 0.5 && cutoff < FULL_BAND_FRACTION * nyquist => {
+            if audio.sample_rate > 48000 && cutoff >= UPSAMPLED_MIN_HZ {
+                Verdict::Upsampled
+            } else {
+                Verdict::Lossy
+            }
+        }
+        _ => Verdict::FullBandwidth,
+    };
+
+    LossyReport {
+        verdict,
+        cutoff_hz,
+        bitrate_class: cutoff_hz
+            .filter(|_| verdict == Verdict::Lossy)
+            .map(bitrate_class),
+        shelf_fraction,
+        blocks,
+    }
+}
+
+// Moving average over SMOOTHING_HZ, in dB
+fn smooth(db: &[f64], bin_width: f64) -> Vec<f64> {
+    let half = ((SMOOTHING_HZ / bin_width / 2.0).round() as usize).max(1);
+    (0..db.len())
+        .map(|i| {
+            let range = &db[i.saturating_sub(half)..(i + half + 1).min(db.len())];
+            range.iter().sum::<f64>() / range.len() as f64
+        })
+        .collect()
+}
+
+// Cutoff bin of a smoothed spectrum and whether it ends in a shelf, `None` when silent
+fn block_cutoff(db: &[f64], bin_width: f64) -> Option<(usize, bool)> {
+    let first = (MIN_CUTOFF_HZ / bin_width).ceil() as usize;
+    let band = db.get(first..).filter(|band| !band.is_empty())?;
+    if band.iter().all(|&level| level < SILENCE_DBFS) {
+        return None;
+    }
+
+    // A spectrum with no bin clearing the floor's margin is flat up to the top bin
+    let floor = band.iter().copied().fold(f64::INFINITY, f64::min);
+    let Some(offset) = band
+        .iter()
+        .rposition(|&level| level > floor + FLOOR_MARGIN_DB)
+    else {
+        return Some((db.len() - 1, false));
+    };
+    let cutoff = first + offset;
+
+    let mean = |from: f64, to: f64| {
+        let from = ((cutoff as f64 + from / bin_width).round().max(0.0) as usize).min(db.len());
+        let to = ((cutoff as f64 + to / bin_width).round().max(0.0) as usize).min(db.len());
+        let range = &db[from..to.max(from)];
+        (!range.is_empty()).then(|| range.iter().sum::<f64>() / range.len() as f64)
+    };
+    let shelf = match (
+        mean(-SHELF_FAR_HZ, -SHELF_NEAR_HZ),
+        mean(SHELF_NEAR_HZ, SHELF_FAR_HZ),
+    ) {
+        (Some(below), Some(above)) => below - above >= SHELF_DB,
+        _ => false,
+    };
+    Some((cutoff, shelf))
+}
+
+// Typical bitrate of MP3 and AAC encoders for a lowpass frequency
+fn bitrate_class(cutoff_hz: f64) -> &'static str {
+    match cutoff_hz {
+        c if c < 11500.0 => "64 kbps or lower",
+        c if c < 15500.0 => "96 kbps",
+        c if c < 17500.0 => "128 kbps",
+        c if c < 19000.0 => "160-192 kbps",
+        c if c < 20000.0 => "224-256 kbps",
+        _ => "320 kbps",
+    }
+}
+
+// Trace the cutoff of every block ending in a shelf over the plot
+pub fn draw_cutoff(
+    img: &mut RgbaImage,
+    plot: &Rect,
+    report: &LossyReport,
+    duration: f64,
+    (min_freq, max_freq): (f32, f32),
+    palette: &Palette,
+) {
+    let x_of = |time: f64| plot.x as f32 + (time / duration * plot.width as f64) as f32;
+    for block in report.blocks.iter().filter(|b| b.shelf) {
+        let Some(cutoff) = block.cutoff_hz else {
+            continue;
+        };
+        let fraction = (cutoff as f32 - min_freq) / (max_freq - min_freq);
+        if !(0.0..=1.0).contains(&fraction) {
+            continue;
+        }
+
+        let y = crate::frequency_y(plot, fraction);
+        let (left, right) = (
+            x_of(block.start_s),
+            x_of(block.end_s).min(plot.right() as f32),
+        );
+        // Two pixels thick to stand out against the spectrogram
+        for y in [y, (y + 1.0).min(plot.bottom() as f32 - 1.0)] {
+            draw_line_segment_mut(img, (left, y), (right, y), palette.lossy_cutoff);
+        }
+    }
+}
+
+// Verdict and cutoff, with the source bitrate they suggest when it can be estimated
+pub fn summary(report: &LossyReport) -> Vec<String> {
+    let mut lines = vec![format!("Verdict: {}", report.verdict.description())];
+    match report.cutoff_hz {
+        Some(cutoff) => lines.push(format!(
+            "Cutoff: {:.1} kHz in {:.0}% of blocks",
+            cutoff / 1000.0,
+            report.shelf_fraction * 100.0
+        )),
+        None => lines.push("Cutoff: no shelf found".to_string()),
+    }
+    if let Some(bitrate) = report.bitrate_class {
+        lines.push(format!("Estimated source bitrate: {}", bitrate));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::white_noise;
+    use rustfft::{FftPlanner, num_complex::Complex};
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    // White noise with everything above `cutoff_hz` removed, like an encoder's lowpass, and
+    // quantized to 16 bits as decoded files are
+    fn lowpassed_noise(length: usize, cutoff_hz: f64) -> Vec<f32> {
+        let mut buffer: Vec<Complex<f32>> = white_noise(length)
+            .iter()
+            .map(|&s| Complex::new(s, 0.0))
+            .collect();
+        let mut planner = FftPlanner::new();
+        planner.plan_fft_forward(length).process(&mut buffer);
+        let cutoff = (cutoff_hz * length as f64 / SAMPLE_RATE as f64) as usize;
+        for (bin, value) in buffer.iter_mut().enumerate() {
+            if bin.min(length - bin) > cutoff {
+                *value = Complex::new(0.0, 0.0);
+            }
+        }
+        planner.plan_fft_inverse(length).process(&mut buffer);
+        buffer
+            .iter()
+            .map(|c| (c.re / length as f32 * 32768.0).round() / 32768.0)
+            .collect()
+    }
+
+    fn mono(samples: Vec<f32>) -> DecodedAudio {
+        crate::test_signals::mono(samples, SAMPLE_RATE)
+    }
+
+    #[test]
+    fn white_noise_is_full_bandwidth() {
+        let report = analyze(&mono(white_noise(SAMPLE_RATE as usize * 5)));
+        assert_eq!(report.verdict, Verdict::FullBandwidth);
+        assert!(report.blocks.iter().all(|block| block.cutoff_hz.is_some()));
+        assert!(report.cutoff_hz.is_none());
+    }
+
+    #[test]
+    fn lowpassed_noise_is_lossy() {
+        let report = analyze(&mono(lowpassed_noise(SAMPLE_RATE as usize * 5, 16000.0)));
+        assert_eq!(report.verdict, Verdict::Lossy);
+        let cutoff = report.cutoff_hz.expect("No cutoff");
+        assert!((cutoff - 16000.0).abs() < 300.0, "cutoff {}", cutoff);
+        assert_eq!(report.bitrate_class, Some("128 kbps"));
+    }
+
+    #[test]
+    fn silence_is_inconclusive() {
+        let report = analyze(&mono(vec![0.0; SAMPLE_RATE as usize * 3]));
+        assert_eq!(report.verdict, Verdict::Inconclusive);
+    }
+}
@@ -0,0 +1,390 @@
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_line_segment_mut;
+use serde::Serialize;
+
+use crate::audio::DecodedAudio;
+use crate::layout::Rect;
+use crate::theme::Palette;
+
+// Loudness values are computed from 100 ms steps
+const STEP_SECONDS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+// Range of the loudness panel, top to bottom
+pub const PANEL_RANGE_LUFS: (f64, f64) = (0.0, -60.0);
+
+// Loudness measurements after ITU-R BS.1770-4 and EBU Tech 3341/3342. Values are `None` when
+// the audio is silent or too short to measure.
+#[derive(Serialize)]
+pub struct Loudness {
+    pub integrated_lufs: Option<f64>,
+    pub loudness_range_lu: Option<f64>,
+    pub max_momentary_lufs: Option<f64>,
+    pub max_short_term_lufs: Option<f64>,
+    pub true_peak_dbtp: Option<f64>,
+    pub channel_true_peaks_dbtp: Vec<Option<f64>>,
+    /// Momentary (400 ms) loudness every 100 ms, timed at the end of each window
+    pub momentary: Vec<LoudnessPoint>,
+    /// Short-term (3 s) loudness every 100 ms, timed at the end of each window
+    pub short_term: Vec<LoudnessPoint>,
+}
+
+#[derive(Serialize)]
+pub struct LoudnessPoint {
+    pub time_s: f64,
+    pub lufs: Option<f64>,
+}
+
+pub fn measure(audio: &DecodedAudio) -> Loudness {
+    let step = ((audio.sample_rate as f64 * STEP_SECONDS).round() as usize).max(1);
+
+    // Channel-weighted energy of the K-weighted signal per 100 ms step
+    let steps = audio.frames() / step;
+    let mut step_energy = vec![0.0f64; steps];
+    for (channel, role) in audio.channels.iter().zip(&audio.roles) {
+        let weight = role.weight();
+        if weight == 0.0 {
+            continue;
+        }
+        let mut filter = KWeighting::new(audio.sample_rate as f64);
+        for (energy, chunk) in step_energy.iter_mut().zip(channel.chunks_exact(step)) {
+            let sum: f64 = chunk
+                .iter()
+                .map(|&s| filter.process(s as f64).powi(2))
+                .sum();
+            *energy += weight * sum;
+        }
+    }
+
+    let momentary = block_energies(&step_energy, MOMENTARY_STEPS, step);
+    let short_term = block_energies(&step_energy, SHORT_TERM_STEPS, step);
+
+    let channel_peaks: Vec<f64> = audio
+        .channels
+        .iter()
+        .map(|channel| true_peak(channel, audio.sample_rate))
+        .collect();
+    let true_peak = channel_peaks.iter().copied().fold(0.0, f64::max);
+
+    Loudness {
+        integrated_lufs: integrated(&momentary),
+        loudness_range_lu: loudness_range(&short_term),
+        max_momentary_lufs: momentary
+            .iter()
+            .copied()
+            .map(to_lufs)
+            .fold(None, max_option),
+        max_short_term_lufs: short_term
+            .iter()
+            .copied()
+            .map(to_lufs)
+            .fold(None, max_option),
+        true_peak_dbtp: to_db(true_peak),
+        channel_true_peaks_dbtp: channel_peaks.into_iter().map(to_db).collect(),
+        momentary: curve(&momentary, MOMENTARY_STEPS),
+        short_term: curve(&short_term, SHORT_TERM_STEPS),
+    }
+}
+
+// Mean square of every window of `length` steps, advancing one step at a time
+fn block_energies(step_energy: &[f64], length: usize, step: usize) -> Vec<f64> {
+    step_energy
+        .windows(length)
+        .map(|window| window.iter().sum::<f64>() / (length * step) as f64)
+        .collect()
+}
+
+fn curve(energies: &[f64], length: usize) -> Vec<LoudnessPoint> {
+    energies
+        .iter()
+        .enumerate()
+        .map(|(i, &energy)| LoudnessPoint {
+            time_s: (i + length) as f64 * STEP_SECONDS,
+            lufs: to_lufs(energy),
+        })
+        .collect()
+}
+
+fn to_lufs(energy: f64) -> Option<f64> {
+    (energy > 0.0).then(|| -0.691 + 10.0 * energy.log10())
+}
+
+fn to_db(amplitude: f64) -> Option<f64> {
+    (amplitude > 0.0).then(|| 20.0 * amplitude.log10())
+}
+
+fn max_option(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// Blocks above the absolute gate and above the relative gate derived from those
+fn gated(energies: &[f64], relative_gate: f64) -> Vec<f64> {
+    let above_absolute: Vec<f64> = energies
+        .iter()
+        .copied()
+        .filter(|&e| to_lufs(e).is_some_and(|l| l > ABSOLUTE_GATE_LUFS))
+        .collect();
+    if above_absolute.is_empty() {
+        return Vec::new();
+    }
+
+    let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+    let threshold = to_lufs(mean).unwrap_or(f64::NEG_INFINITY) + relative_gate;
+    above_absolute
+        .into_iter()
+        .filter(|&e| to_lufs(e).is_some_and(|l| l > threshold))
+        .collect()
+}
+
+fn integrated(momentary: &[f64]) -> Option<f64> {
+    let blocks = gated(momentary, INTEGRATED_RELATIVE_GATE_LU);
+    if blocks.is_empty() {
+        return None;
+    }
+    to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64)
+}
+
+// Spread between the 10th and 95th percentile of gated short-term loudness
+fn loudness_range(short_term: &[f64]) -> Option<f64> {
+    let mut levels: Vec<f64> = gated(short_term, RANGE_RELATIVE_GATE_LU)
+        .into_iter()
+        .filter_map(to_lufs)
+        .collect();
+    if levels.is_empty() {
+        return None;
+    }
+    levels.sort_by(f64::total_cmp);
+    let percentile = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+    Some(percentile(0.95) - percentile(0.10))
+}
+
+// Two-stage K-weighting filter (high shelf followed by high pass), for any sample rate
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        use std::f64::consts::PI;
+
+        // Shelving pre-filter modelling the acoustic effect of the head
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // RLB high-pass filter
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        KWeighting {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.stages
+            .iter_mut()
+            .fold(sample, |value, stage| stage.process(value))
+    }
+}
+
+// Direct form II transposed biquad with a0 normalised to 1
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Biquad {
+            b,
+            a,
+            state: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+// Taps per polyphase branch of the true-peak interpolator
+const TRUE_PEAK_TAPS: usize = 12;
+
+// Highest absolute value of the signal oversampled to at least 192 kHz
+fn true_peak(samples: &[f32], sample_rate: u32) -> f64 {
+    let sample_peak = samples
+        .iter()
+        .fold(0.0f64, |peak, &s| peak.max(s.abs() as f64));
+    let factor = match sample_rate {
+        0..96_000 => 4,
+        96_000..192_000 => 2,
+        _ => return sample_peak,
+    };
+
+    // Windowed-sinc low-pass at the original Nyquist frequency, split into polyphase branches
+    let length = TRUE_PEAK_TAPS * factor;
+    let center = (length - 1) as f64 / 2.0;
+    let taps: Vec<f64> = (0..length)
+        .map(|n| {
+            let t = (n as f64 - center) / factor as f64;
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                (std::f64::consts::PI * t).sin() / (std::f64::consts::PI * t)
+            };
+            let window =
+                0.5 - 0.5 * (2.0 * std::f64::consts::PI * n as f64 / (length - 1) as f64).cos();
+            sinc * window
+        })
+        .collect();
+
+    let mut peak = sample_peak;
+    for i in TRUE_PEAK_TAPS..=samples.len() {
+        let history = &samples[i - TRUE_PEAK_TAPS..i];
+        for phase in 0..factor {
+            let value: f64 = history
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(k, &s)| s as f64 * taps[k * factor + phase])
+                .sum();
+            peak = peak.max(value.abs());
+        }
+    }
+    peak
+}
+
+// Momentary and short-term curves over a panel spanning PANEL_RANGE_LUFS, with the integrated
+// loudness as a horizontal line
+pub fn draw_loudness(
+    img: &mut RgbaImage,
+    panel: &Rect,
+    loudness: &Loudness,
+    duration: f64,
+    palette: &Palette,
+) {
+    let (top_lufs, bottom_lufs) = PANEL_RANGE_LUFS;
+    let x_of = |time: f64| panel.x as f64 + time / duration * panel.width as f64;
+    let y_of = |lufs: f64| {
+        let fraction = ((lufs - bottom_lufs) / (top_lufs - bottom_lufs)).clamp(0.0, 1.0);
+        panel.bottom() as f64 - fraction * panel.height as f64
+    };
+
+    let mut draw_curve = |points: &[LoudnessPoint], color: Rgba<u8>| {
+        // Silent stretches leave gaps in the curve
+        for pair in points.windows(2) {
+            if let (Some(a), Some(b)) = (pair[0].lufs, pair[1].lufs) {
+                draw_line_segment_mut(
+                    img,
+                    (x_of(pair[0].time_s) as f32, y_of(a) as f32),
+                    (x_of(pair[1].time_s) as f32, y_of(b) as f32),
+                    color,
+                );
+            }
+        }
+    };
+    draw_curve(&loudness.momentary, palette.loudness_momentary);
+    draw_curve(&loudness.short_term, palette.loudness_short_term);
+
+    if let Some(integrated) = loudness.integrated_lufs {
+        let y = y_of(integrated) as f32;
+        let (left, right) = (panel.x as f32, panel.right() as f32);
+        draw_line_segment_mut(img, (left, y), (right, y), palette.axes);
+    }
+    crate::draw_outline(img, panel, palette.axes);
+}
+
+// Human-readable summary, one value per line
+pub fn summary(loudness: &Loudness) -> Vec<String> {
+    let value = |v: Option<f64>, unit: &str| match v {
+        Some(v) => format!("{:.1} {}", v, unit),
+        None => "n/a".to_string(),
+    };
+    vec![
+        format!(
+            "Integrated loudness: {}",
+            value(loudness.integrated_lufs, "LUFS")
+        ),
+        format!(
+            "Loudness range:      {}",
+            value(loudness.loudness_range_lu, "LU")
+        ),
+        format!(
+            "Max momentary:       {}",
+            value(loudness.max_momentary_lufs, "LUFS")
+        ),
+        format!(
+            "Max short-term:      {}",
+            value(loudness.max_short_term_lufs, "LUFS")
+        ),
+        format!(
+            "True peak:           {}",
+            value(loudness.true_peak_dbtp, "dBTP")
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ChannelRole;
+    use crate::test_signals::{mono, sine};
+
+    // EBU Tech 3341: a 1 kHz sine at -23 dBFS in both stereo channels reads -23.0 LUFS
+    #[test]
+    fn reference_tone_reads_minus_23_lufs() {
+        let tone = sine(1000.0, 10f64.powf(-23.0 / 20.0), 20.0, 48000);
+        let audio = DecodedAudio {
+            channels: vec![tone.clone(), tone],
+            roles: vec![ChannelRole::Front, ChannelRole::Front],
+            sample_rate: 48000,
+        };
+        let loudness = measure(&audio);
+
+        let integrated = loudness.integrated_lufs.expect("No integrated loudness");
+        assert!((integrated + 23.0).abs() < 0.1, "integrated {}", integrated);
+        let momentary = loudness.max_momentary_lufs.expect("No momentary loudness");
+        assert!((momentary + 23.0).abs() < 0.1, "momentary {}", momentary);
+        let range = loudness.loudness_range_lu.expect("No loudness range");
+        assert!(range < 0.1, "range {}", range);
+        let peak = loudness.true_peak_dbtp.expect("No true peak");
+        assert!((peak + 23.0).abs() < 0.2, "true peak {}", peak);
+    }
+
+    #[test]
+    fn silence_has_no_loudness() {
+        let audio = mono(vec![0.0; 48000 * 5], 48000);
+        assert!(measure(&audio).integrated_lufs.is_none());
+    }
+}
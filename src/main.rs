@@ -1,648 +1,1635 @@
-use clap::Parser;
-use hound::{SampleFormat, WavReader};
-use image::{ImageBuffer, Rgb};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgba, RgbaImage};
 use imageproc::drawing::{draw_line_segment_mut, draw_text_mut};
-use rustfft::{FftPlanner, num_complex::Complex};
-use rusttype::{Font, Scale};
+use rusttype::Font;
 use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
 use std::path::Path;
-use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
-use symphonia::core::formats::FormatOptions;
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+
+mod axis;
+mod cli;
+mod compare;
+mod config;
+mod export;
+mod features;
+mod info;
+mod layout;
+mod lossy;
+mod loudness;
+mod montage;
+mod onsets;
+mod pitch;
+mod qc;
+mod report;
+mod spectrum;
+#[cfg(test)]
+mod test_signals;
+mod theme;
+mod waveform;
 
 mod build_time {
     include!(concat!(env!("OUT_DIR"), "/build_time.rs"));
 }
 
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-struct Args {
-    /// Input audio file path (supports WAV, MP3, FLAC, OGG, AAC, etc.)
-    #[arg(short, long, value_name = "FILE")]
-    input: Option<String>,
+use audio_spectrogram::{audio, decode, progress, raw, stft};
+
+use audio::DecodedAudio;
+use axis::{AxisTicks, TimeExtent};
+use cli::{
+    BatchArgs, Cli, Command, CompareArgs, DecodeArgs, ExportArgs, FeaturesArgs, LossyArgs,
+    LoudnessArgs, MontageArgs, OnsetsArgs, PitchArgs, QcArgs, RenderArgs,
+};
+use config::Settings;
+use decode::{STDIN_PATH, audio_tracks, open_format, probe_source, track_duration};
+use layout::{LabelExtents, Layout, MINOR_TICK_LENGTH, Rect, TICK_LENGTH, TextStyle};
+use lossy::LossyReport;
+use loudness::Loudness;
+use onsets::{OnsetReport, OnsetSummary};
+use pitch::{PitchSummary, PitchTrack};
+use progress::{Progress, ProgressBarReporter, Stage};
+use qc::QcReport;
+use report::{BatchFailure, BatchReport, RunReport, Stopwatch, Timings, print_json};
+use stft::{compute_spectrum, magnitude_to_db};
+use theme::Palette;
+
+fn read_audio_samples(
+    path: &str,
+    decode: &DecodeArgs,
+    progress: &dyn Progress,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    // Headerless PCM is described entirely by the command line
+    if let Some(spec) = decode.raw_spec()? {
+        return raw::read_raw_samples(path, &spec, progress);
+    }
+    decode::read_audio(path, decode.track.as_deref(), progress)
+}
 
-    /// Input audio file (position argument, for drag-and-drop support)
-    #[arg(value_name = "INPUT_FILE")]
-    input_file: Option<String>,
+// Magnitude spectra of every full frame, with the window, FFT and hop sizes of the settings
+fn stft_magnitudes(samples: &[f32], settings: &Settings, progress: &dyn Progress) -> Vec<Vec<f32>> {
+    stft::stft_magnitudes(
+        samples,
+        settings.window,
+        settings.fft_size,
+        settings.hop_size,
+        progress,
+    )
+}
 
-    /// Output spectrogram file path
-    #[arg(short, long)]
-    output: Option<String>,
+fn list_tracks(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let format = open_format(path)?;
+    let codecs = symphonia::default::get_codecs();
+
+    println!("Tracks in {}:", path);
+    for (index, track) in audio_tracks(format.tracks()).into_iter().enumerate() {
+        let params = &track.codec_params;
+        let codec = codecs
+            .get_codec(params.codec)
+            .map(|descriptor| descriptor.short_name.to_string())
+            .unwrap_or_else(|| format!("{}", params.codec));
+        let rate = params
+            .sample_rate
+            .map(|rate| format!("{} Hz", rate))
+            .unwrap_or_else(|| "unknown".to_string());
+        let channels = params
+            .channels
+            .map(|channels| channels.count().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let duration = track_duration(params)
+            .map(|seconds| format!("{:.3}s", seconds))
+            .unwrap_or_else(|| "unknown".to_string());
+        let language = track.language.as_deref().unwrap_or("und");
+
+        println!(
+            "  [{}] id={} codec={} rate={} channels={} duration={} language={}",
+            index, track.id, codec, rate, channels, duration, language
+        );
+    }
 
-    /// FFT size
-    #[arg(short, long, default_value_t = 4096)]
-    fft_size: usize,
+    Ok(())
+}
 
-    /// Hop size (defaults to half of FFT size)
-    #[arg(short = 'p', long)]
-    hop_size: Option<usize>,
+fn load_font() -> Font<'static> {
+    let font_data = get_system_font().expect(
+        "Could not find system font. Please ensure at least one monospace font is installed",
+    );
+    Font::try_from_vec(font_data).expect("Invalid font file format")
 }
 
-fn read_audio_samples(path: &str) -> Result<(Vec<f32>, u32), Box<dyn std::error::Error>> {
-    // First try to read as WAV using hound for backward compatibility
-    if path.to_lowercase().ends_with(".wav") {
-        return match read_wav_samples(path) {
-            Ok(result) => Ok(result),
-            Err(e) => {
-                println!("WAV decoding failed, trying generic decoder: {}", e);
-                read_generic_audio(path)
-            }
-        };
-    }
+fn get_system_font() -> Option<Vec<u8>> {
+    let font_path = if cfg!(target_os = "windows") {
+        "C:\\Windows\\Fonts\\consola.ttf"
+    } else if cfg!(target_os = "macos") {
+        "/System/Library/Fonts/Monaco.ttf"
+    } else {
+        "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf"
+    };
 
-    // For other formats, use symphonia
-    read_generic_audio(path)
+    std::fs::read(font_path).ok()
 }
 
-fn read_generic_audio(path: &str) -> Result<(Vec<f32>, u32), Box<dyn std::error::Error>> {
-    // Create a media source from the file
-    let file = File::open(path)?;
-    let media_source = MediaSourceStream::new(Box::new(file), Default::default());
+// Range of FFT bins covering the configured frequency range, which must hold at least one bin
+fn frequency_bins(
+    settings: &Settings,
+    sample_rate: u32,
+) -> Result<std::ops::Range<usize>, Box<dyn std::error::Error>> {
+    let bins = settings.fft_size / 2;
+    let bin_width = sample_rate as f32 / settings.fft_size as f32;
+    let first = ((settings.freq_min / bin_width).ceil() as usize).min(bins);
+    let last = match settings.freq_max {
+        Some(freq_max) => ((freq_max / bin_width).floor() as usize + 1).min(bins),
+        None => bins,
+    };
+    if first < last {
+        return Ok(first..last);
+    }
 
-    // Create a hint to help the format registry guess what format reader is appropriate
-    let mut hint = Hint::new();
-    if let Some(extension) = Path::new(path).extension() {
-        hint.with_extension(extension.to_str().unwrap_or(""));
+    let nyquist = sample_rate as f32 / 2.0;
+    if settings.freq_min >= nyquist {
+        Err(format!(
+            "Frequency range minimum {} Hz is not below the Nyquist frequency of {} Hz",
+            settings.freq_min, nyquist
+        )
+        .into())
+    } else {
+        Err(format!(
+            "Frequency range from {} Hz holds no FFT bin (bins are {:.2} Hz apart)",
+            settings.freq_min, bin_width
+        )
+        .into())
     }
+}
 
-    // Use the default options for format and metadata
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
+// Frequency bins of a run's plot or export, ending the run when the range holds none
+fn bins_or_exit(settings: &Settings, sample_rate: u32) -> std::ops::Range<usize> {
+    frequency_bins(settings, sample_rate).unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1);
+    })
+}
 
-    // Probe the media source to determine the format
-    let probed = symphonia::default::get_probe().format(
-        &hint,
-        media_source,
-        &format_opts,
-        &metadata_opts,
-    )?;
+// Frequencies in Hz at the bottom and top edges of the plotted bin range
+fn frequency_range(
+    bins: &std::ops::Range<usize>,
+    settings: &Settings,
+    sample_rate: u32,
+) -> (f32, f32) {
+    let bin_width = sample_rate as f32 / settings.fft_size as f32;
+    (bins.start as f32 * bin_width, bins.end as f32 * bin_width)
+}
 
-    // Get the format reader
-    let mut format = probed.format;
+// Colors of the plot and the matching colorbar labels
+struct ColorScale {
+    gradient: colorgrad::Gradient,
+    /// Values at the bottom and top of the colorbar
+    range: (f32, f32),
+    /// Candidate steps between colorbar labels, finest first
+    steps: Vec<f64>,
+    label: fn(f64) -> String,
+}
 
-    // Find audio track
-    let track = format
-        .tracks()
-        .iter()
-        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
-        .ok_or("No valid audio track found. If this is an OGG file, it might contain cover art.")?;
+impl ColorScale {
+    fn decibels(settings: &Settings) -> Self {
+        ColorScale {
+            gradient: settings.colormap.gradient(),
+            range: (settings.db_min, settings.db_max),
+            steps: axis::decibel_steps((settings.db_max - settings.db_min) as f64),
+            label: |db| format!("{:.0}dB", db),
+        }
+    }
 
-    let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+    // Colorbar labels spaced to fit a colorbar of the given height, as fractions of it
+    fn ticks(&self, height: f32, style: &TextStyle) -> Vec<(f32, String)> {
+        let label_height = style.digit_height();
+        axis::colorbar_ticks(
+            &self.steps,
+            (self.range.0 as f64, self.range.1 as f64),
+            height,
+            &self.label,
+            &|_| label_height,
+        )
+    }
+}
 
-    // Create a decoder for the track
-    let mut decoder = match symphonia::default::get_codecs()
-        .make(&track.codec_params, &DecoderOptions::default())
-    {
-        Ok(decoder) => decoder,
-        Err(e) => {
-            println!("Failed to create decoder: {}", e);
-            println!("Please ensure the correct codec features are enabled.");
-            return Err(e.into());
-        }
+// Analysed signal behind a plot, for its axes and the panels around it
+struct PlotSource<'a> {
+    samples: &'a [f32],
+    sample_rate: u32,
+    /// One column per frame; magnitude spectra unless the plot shows derived values
+    frames: &'a [Vec<f32>],
+    bins: std::ops::Range<usize>,
+}
+
+fn generate_spectrogram(
+    audio: &DecodedAudio,
+    measurements: &Measurements,
+    settings: &Settings,
+    progress: &dyn Progress,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let samples = &audio.mono();
+
+    let bins = frequency_bins(settings, audio.sample_rate)?;
+
+    // Calculate all spectral values
+    let frames = stft_magnitudes(samples, settings, progress);
+    let source = PlotSource {
+        samples,
+        sample_rate: audio.sample_rate,
+        frames: &frames,
+        bins,
     };
 
-    let mut merged_samples = Vec::new();
+    let scale = ColorScale::decibels(settings);
+    let plot_image = render_plot(&frames, &source.bins, &scale, magnitude_to_db, progress);
+    draw_figure(
+        plot_image,
+        &source,
+        &scale,
+        measurements,
+        settings,
+        progress,
+    )
+}
 
-    // Decode the audio packets
-    while let Ok(packet) = format.next_packet() {
-        // Decode the packet into audio samples
-        let decoded = decoder.decode(&packet)?;
+// Place a rendered plot in the full image with axes, colorbar and the enabled panels
+fn draw_figure(
+    plot_image: RgbaImage,
+    source: &PlotSource,
+    scale: &ColorScale,
+    measurements: &Measurements,
+    settings: &Settings,
+    progress: &dyn Progress,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    if settings.no_axes {
+        // Bare pixel data has no decorations to show in place of an empty plot; the frequency
+        // range always holds a bin, so only a lack of frames leaves it empty
+        if plot_image.width() == 0 {
+            return Err(format!(
+                "Input too short for an FFT size of {} ({} samples), nothing to draw",
+                settings.fft_size,
+                source.samples.len()
+            )
+            .into());
+        }
+        // Bare pixel data, optionally resampled, without any text or decorations
+        let plot_image = match settings.size {
+            Some(size) => {
+                imageops::resize(&plot_image, size.width, size.height, FilterType::Triangle)
+            }
+            None => plot_image,
+        };
+        return Ok(output_image(plot_image, false));
+    }
 
-        // Get the audio buffer specification
-        let spec = *decoded.spec();
-        let num_channels = spec.channels.count();
+    let font = load_font();
+    let (img, _) = compose_figure(
+        plot_image,
+        source,
+        scale,
+        measurements,
+        settings,
+        &font,
+        progress,
+    );
+    Ok(output_image(img, settings.palette().background[3] < 255))
+}
 
-        // Create the sample buffer
-        let mut sample_buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+// Full image around a rendered plot, together with the positions of its elements
+fn compose_figure(
+    plot_image: RgbaImage,
+    source: &PlotSource,
+    scale: &ColorScale,
+    measurements: &Measurements,
+    settings: &Settings,
+    font: &Font,
+    progress: &dyn Progress,
+) -> (RgbaImage, Layout) {
+    let (samples, sample_rate, frames) = (source.samples, source.sample_rate, source.frames);
+    // Drawing goes through a few steps: layout, panels, overlays, then axes and colorbar
+    const DRAW_STEPS: u64 = 4;
+    let drawn = |step: u64| progress.update(Stage::Draw, step, DRAW_STEPS);
+    let bins = source.bins.clone();
 
-        // Copy the decoded audio samples into the sample buffer
-        sample_buf.copy_interleaved_ref(decoded);
+    // Calculate main plotting area dimensions
+    let num_frames = frames.len() as u32;
+    let height = bins.len() as u32;
+
+    let axis_style = TextStyle::new(font, settings.font_size);
+    let colorbar_style = TextStyle::new(font, settings.font_size * 5.0 / 6.0);
+
+    // Place ticks first so the margins can be fitted to their labels
+    let colorbar_ticks = scale.ticks(height as f32, &colorbar_style);
+    let (min_freq, max_freq) = frequency_range(&bins, settings, sample_rate);
+    let label_height = axis_style.digit_height();
+    let mut freq_ticks = axis::frequency_ticks(
+        (min_freq, max_freq),
+        settings.freq_unit,
+        height as f32,
+        settings.minor_ticks,
+        &|_| label_height,
+    );
+    // Label the top edge (e.g. Nyquist) when it does not crowd the last tick label
+    let last_fraction = freq_ticks
+        .major
+        .last()
+        .map_or(0.0, |(fraction, _)| *fraction);
+    if (1.0 - last_fraction) * height as f32 - 1.0 >= label_height * 1.5 {
+        let label = axis::frequency_edge_label(max_freq, settings.freq_unit);
+        freq_ticks.major.push((1.0, label));
+    }
 
-        let samples = sample_buf.samples();
+    let extent = TimeExtent {
+        seconds: samples.len() as f64 / sample_rate as f64,
+        samples: samples.len(),
+        frames: frames.len(),
+    };
+    let time_ticks = axis::time_ticks(
+        &extent,
+        settings.time_format,
+        num_frames as f32,
+        settings.minor_ticks,
+        &|text| axis_style.width(text),
+    );
 
-        // Process samples in groups of channels
-        for chunk in samples.chunks(num_channels) {
-            let mut sum = 0.0;
-            let mut count = 0;
+    // Full scale at the waveform panel's edges
+    let amplitude_ticks = AxisTicks {
+        major: ["-1.0", "0.0", "1.0"]
+            .iter()
+            .enumerate()
+            .map(|(i, label)| (i as f32 / 2.0, label.to_string()))
+            .collect(),
+        minor: Vec::new(),
+    };
 
-            // Only use first two channels if available
-            let channels_to_use = num_channels.min(2);
-            for channel in 0..channels_to_use {
-                if let Some(&sample) = chunk.get(channel) {
-                    sum += sample;
-                    count += 1;
-                }
-            }
+    // Every 20 LU over the loudness panel
+    let (top_lufs, bottom_lufs) = loudness::PANEL_RANGE_LUFS;
+    let loudness_ticks = AxisTicks {
+        major: (0..=((top_lufs - bottom_lufs) / 20.0) as i32)
+            .map(|i| {
+                let lufs = bottom_lufs + 20.0 * i as f64;
+                let fraction = (lufs - bottom_lufs) / (top_lufs - bottom_lufs);
+                (fraction as f32, format!("{:.0}LUFS", lufs))
+            })
+            .collect(),
+        minor: Vec::new(),
+    };
 
-            if count > 0 {
-                merged_samples.push(sum / count as f32);
-            }
-        }
+    // Features for the overlay and the feature panel, one per frame
+    let features = (settings.feature_overlay || settings.feature_panel.is_some())
+        .then(|| features::extract(samples, frames, sample_rate, settings));
+    let feature_scale = settings
+        .feature_panel
+        .zip(features.as_ref())
+        .map(|(feature, values)| {
+            let max = features::panel_max(values, feature);
+            let ticks = feature.panel_ticks(
+                max,
+                settings.freq_unit,
+                settings.feature_height as f32,
+                &|_| label_height,
+            );
+            (feature, max, ticks)
+        });
+
+    let widest = |ticks: &[(f32, String)], style: &TextStyle| {
+        ticks
+            .iter()
+            .map(|(_, label)| style.width(label))
+            .fold(0.0, f32::max)
+    };
+    let overhang = |edge: fn(f32) -> f32| {
+        time_ticks
+            .major
+            .iter()
+            .map(|(fraction, label)| {
+                axis_style.width(label) / 2.0 - edge(*fraction) * num_frames as f32
+            })
+            .fold(0.0, f32::max)
+    };
+    // Panels above the plot share the left margin with the frequency axis
+    let mut left_scales = vec![&freq_ticks];
+    if settings.waveform {
+        left_scales.push(&amplitude_ticks);
     }
+    if settings.loudness {
+        left_scales.push(&loudness_ticks);
+    }
+    if let Some((_, _, ticks)) = &feature_scale {
+        left_scales.push(ticks);
+    }
+    let labels = LabelExtents {
+        frequency: left_scales
+            .iter()
+            .map(|ticks| widest(&ticks.major, &axis_style))
+            .fold(0.0, f32::max),
+        time_overhang: (overhang(|f| f), overhang(|f| 1.0 - f)),
+        colorbar: widest(&colorbar_ticks, &colorbar_style),
+    };
+    let layout = Layout::compute(
+        num_frames,
+        height,
+        &settings.layout_options(),
+        &labels,
+        &axis_style,
+    );
 
-    Ok((merged_samples, sample_rate))
-}
-
-fn read_wav_samples(path: &str) -> Result<(Vec<f32>, u32), hound::Error> {
-    let reader = WavReader::open(path)?;
-    let sample_rate = reader.spec().sample_rate;
-    let sample_format = reader.spec().sample_format;
-    let channels = reader.spec().channels as usize;
-    println!("{:?}", reader.spec());
-
-    let mut channel_samples: Vec<Vec<f32>> = vec![Vec::new(); channels];
+    drawn(1);
 
-    // First collect all channel data
-    match sample_format {
-        SampleFormat::Int => {
-            let samples: Vec<i32> = reader.into_samples::<i32>().map(|s| s.unwrap()).collect();
-            let max_value = samples.iter().fold(0, |a, &b| a.max(b.abs()));
+    let palette = settings.palette();
+    let mut img = ImageBuffer::from_pixel(layout.width, layout.height, palette.background);
+    let plot = layout.plot;
+    imageops::replace(&mut img, &plot_image, plot.x as i64, plot.y as i64);
 
-            // Distribute samples to channels
-            for (i, &sample) in samples.iter().enumerate() {
-                let channel = i % channels;
-                channel_samples[channel].push(sample as f32 / max_value as f32);
+    // Grid lines at the frequency ticks, also when the axis itself is hidden
+    if settings.grid {
+        for (fraction, _) in &freq_ticks.major {
+            if *fraction > 0.0 && *fraction < 1.0 {
+                let y = frequency_y(&plot, *fraction) as u32;
+                draw_grid_line(&mut img, y, &plot, palette.grid);
             }
         }
-        SampleFormat::Float => {
-            let samples: Vec<f32> = reader.into_samples::<f32>().map(|s| s.unwrap()).collect();
+    }
 
-            // Distribute samples to channels
-            for (i, &sample) in samples.iter().enumerate() {
-                let channel = i % channels;
-                channel_samples[channel].push(sample);
-            }
+    // Waveform panel above the plot, one column per frame like the spectrogram
+    if let Some(panel) = layout.waveform {
+        let envelopes = waveform::column_envelopes(samples, frames.len());
+        waveform::draw_waveform(&mut img, &panel, &envelopes, &palette);
+        if layout.frequency_axis {
+            let gap = layout.label_gap;
+            draw_vertical_scale(
+                &mut img,
+                &panel,
+                gap,
+                &axis_style,
+                &amplitude_ticks,
+                &palette,
+            );
         }
     }
 
-    // Merge channels (average of available channels)
-    let mut merged_samples = Vec::with_capacity(channel_samples[0].len());
-    for i in 0..channel_samples[0].len() {
-        let mut sum = 0.0;
-        let mut count = 0;
+    // Loudness panel, timed like the spectrogram's time axis
+    if let (Some(panel), Some(loudness)) = (layout.loudness, &measurements.loudness) {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        loudness::draw_loudness(&mut img, &panel, loudness, duration, &palette);
+        if layout.frequency_axis {
+            let gap = layout.label_gap;
+            draw_vertical_scale(
+                &mut img,
+                &panel,
+                gap,
+                &axis_style,
+                &loudness_ticks,
+                &palette,
+            );
+        }
+    }
 
-        // Only use first two channels (left and right) if available
-        let channels_to_use = channels.min(2);
-        for channel in 0..channels_to_use {
-            sum += channel_samples[channel][i];
-            count += 1;
+    // Feature panel, one column per frame like the spectrogram
+    if let (Some(panel), Some(values), Some((feature, max, ticks))) =
+        (layout.features, &features, &feature_scale)
+    {
+        features::draw_panel(&mut img, &panel, values, *feature, *max, &palette);
+        if layout.frequency_axis {
+            let gap = layout.label_gap;
+            draw_vertical_scale(&mut img, &panel, gap, &axis_style, ticks, &palette);
         }
-        merged_samples.push(sum / count as f32);
     }
 
-    Ok((merged_samples, sample_rate))
-}
+    drawn(2);
 
-fn compute_spectrum(samples: &[f32], fft_size: usize) -> Vec<f32> {
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(fft_size);
+    // Problem markers along the bottom edge of the plot
+    if let Some(report) = &measurements.qc {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        qc::draw_markers(&mut img, &plot, report, duration, &palette);
+    }
 
-    // 1. Apply Hanning window and convert to complex input
-    let window: Vec<f32> = (0..fft_size)
-        .map(|i| {
-            0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / (fft_size as f32 - 1.0)).cos())
-        })
-        .collect();
-    let mut input: Vec<Complex<f32>> = samples
-        .iter()
-        .take(fft_size)
-        .zip(window.iter())
-        .map(|(s, w)| Complex::new(s * w, 0.0))
-        .collect();
+    // Detected bandwidth cutoff over the plot
+    if let Some(report) = &measurements.lossy {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        let range = (min_freq, max_freq);
+        lossy::draw_cutoff(&mut img, &plot, report, duration, range, &palette);
+    }
 
-    // 2. Perform FFT
-    fft.process(&mut input);
+    // Spectral centroid and rolloff over the plot
+    if let Some(values) = features.as_ref().filter(|_| settings.feature_overlay) {
+        let range = (min_freq, max_freq);
+        features::draw_overlay(&mut img, &plot, values, range, &palette);
+    }
 
-    // 3. Compute magnitude spectrum
-    input[..fft_size / 2].iter().map(|c| c.norm()).collect()
-}
+    // F0 contour over the plot
+    if let Some(track) = &measurements.pitch {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        let range = (min_freq, max_freq);
+        pitch::draw_contour(&mut img, &plot, track, duration, range, &palette);
+    }
 
-fn get_system_font() -> Option<Vec<u8>> {
-    let font_path = if cfg!(target_os = "windows") {
-        "C:\\Windows\\Fonts\\consola.ttf"
-    } else if cfg!(target_os = "macos") {
-        "/System/Library/Fonts/Monaco.ttf"
-    } else {
-        "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf"
-    };
+    // Onset and beat markers over the plot
+    if let Some(report) = &measurements.onsets {
+        let duration = samples.len() as f64 / sample_rate as f64;
+        let shown = (settings.onsets, settings.beats);
+        onsets::draw_markers(&mut img, &plot, report, duration, shown, &palette);
+    }
 
-    std::fs::read(font_path).ok()
-}
+    drawn(3);
 
-fn generate_spectrogram(
-    samples: &[f32],
-    sample_rate: u32,
-    fft_size: usize,
-    hop_size: usize,
-) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    // Set margins for scale drawing
-    let margin_left = 160u32; // Left margin for frequency scale
-    let margin_right = 180u32; // Right margin, symmetric with left
-    let margin_top = 60u32; // Top margin, symmetric with bottom
-    let margin_bottom = 60u32; // Bottom margin for time scale
+    // Average spectrum right of the plot, sharing its frequency rows
+    if let Some(panel) = layout.spectrum {
+        let average = spectrum::average_spectrum(frames, &bins);
+        let db_range = (settings.db_min, settings.db_max);
+        spectrum::draw_average_spectrum(&mut img, &panel, &average, db_range, &palette);
+        draw_spectrum_labels(&mut img, &panel, &layout, &axis_style, db_range, &palette);
+    }
 
-    // Calculate main plotting area dimensions
-    let num_frames = if samples.len() >= fft_size {
-        (samples.len() - fft_size) / hop_size + 1
+    // Draw axes
+    if layout.frequency_axis {
+        let gap = layout.label_gap;
+        draw_vertical_scale(&mut img, &plot, gap, &axis_style, &freq_ticks, &palette);
+    }
+    if layout.time_axis {
+        draw_line_segment_mut(
+            &mut img,
+            (plot.x as f32, plot.bottom() as f32),
+            (plot.right() as f32, plot.bottom() as f32),
+            palette.axes,
+        );
+        draw_time_scale(&mut img, &layout, &axis_style, &time_ticks, &palette);
+    }
+
+    // Draw colorbar legend on the right
+    if let Some(colorbar) = layout.colorbar {
+        draw_colorbar_with_scale(
+            &mut img,
+            &colorbar,
+            &layout,
+            &colorbar_style,
+            &scale.gradient,
+            &colorbar_ticks,
+            &palette,
+        );
+    }
+    drawn(4);
+
+    (img, layout)
+}
+
+// Drop the alpha channel unless the background is see-through
+fn output_image(img: RgbaImage, transparent: bool) -> DynamicImage {
+    let img = DynamicImage::ImageRgba8(img);
+    if transparent {
+        img
     } else {
-        0
-    };
-    let height = fft_size / 2;
-    // println!("num_frames: {}, height: {}", num_frames, height);
-
-    // Calculate colorbar position and dimensions
-    let colorbar_x = margin_left + (num_frames as u32) + 40; // Colorbar position
-    let colorbar_width = 30u32; // Colorbar width
-    let colorbar_height = height as u32; // Colorbar height same as spectrogram
-    let min_needed_width = colorbar_x + colorbar_width + 100; // Extra space for dB scale text
-
-    // Create image with margins and colorbar space
-    let total_width = min_needed_width;
-    let total_height = (height as u32) + margin_top + margin_bottom;
-    let mut img = ImageBuffer::from_fn(total_width, total_height, |_, _| Rgb([255, 255, 255]));
-
-    // Store all spectral values to calculate global min/max
-    let mut all_magnitudes = Vec::new();
-    let gradient = colorgrad::turbo();
-
-    // First calculate all spectral values
-    for i in 0..num_frames {
-        let start = i * hop_size;
-        if start + fft_size > samples.len() {
-            break;
-        }
-        let chunk = &samples[start..start + fft_size];
-        let spectrum = compute_spectrum(chunk, fft_size);
-        all_magnitudes.extend(spectrum);
+        DynamicImage::ImageRgb8(img.to_rgb8())
     }
+}
 
-    // Draw spectrogram body
-    for (x, i) in (0..num_frames).enumerate() {
-        let start = i * hop_size;
-        if start + fft_size > samples.len() {
-            break;
-        }
-        let chunk = &samples[start..start + fft_size];
-        let spectrum = compute_spectrum(chunk, fft_size);
-
-        for (y, &magnitude) in spectrum.iter().enumerate() {
-            let db_min = -120.0;
-            let db_max = 0.0;
-            let denom = db_max - db_min;
-            let log_mag = if magnitude > 1e-10 {
-                magnitude.log10()
-            } else {
-                -10.0
-            };
-            let db_val = log_mag * 20.0;
-            let mut normalized = (db_val - db_min) / denom;
+// Spectrogram body with one column per frame and one row per bin, low frequencies at the bottom.
+// `to_value` converts frame data to the unit of the color scale, e.g. magnitudes to dB.
+fn render_plot(
+    frames: &[Vec<f32>],
+    bins: &std::ops::Range<usize>,
+    scale: &ColorScale,
+    to_value: fn(f32) -> f32,
+    progress: &dyn Progress,
+) -> RgbaImage {
+    let gradient = &scale.gradient;
+    let (value_min, value_max) = scale.range;
+    let height = bins.len() as u32;
+    let mut img = ImageBuffer::new(frames.len() as u32, height);
+
+    for (x, spectrum) in frames.iter().enumerate() {
+        progress.update(Stage::Render, x as u64 + 1, frames.len() as u64);
+        for (y, &value) in spectrum[bins.clone()].iter().enumerate() {
+            let denom = value_max - value_min;
+            let mut normalized = (to_value(value) - value_min) / denom;
             if !normalized.is_finite() {
                 normalized = 0.0;
             }
-            normalized = normalized.max(0.0).min(1.0);
+            normalized = normalized.clamp(0.0, 1.0);
 
             let color = gradient.at(normalized as f64).to_rgba8();
-            let y_pos = total_height - margin_bottom - (y as u32) - 1;
-
-            // Only draw within valid spectrogram area
-            if y_pos >= margin_top && y_pos < (total_height - margin_bottom) {
-                img.put_pixel(
-                    (x as u32) + margin_left,
-                    y_pos,
-                    Rgb([color[0], color[1], color[2]]),
-                );
-            }
+            img.put_pixel(x as u32, height - (y as u32) - 1, Rgba(color));
         }
     }
 
-    // Load font
-    let font_data = get_system_font().expect(
-        "Could not find system font. Please ensure at least one monospace font is installed",
-    );
-    let font = Font::try_from_bytes(&font_data).expect("Invalid font file format");
+    img
+}
 
-    // Draw axes
-    let black = Rgb([0, 0, 0]);
-    // Vertical axis (frequency)
-    draw_line_segment_mut(
-        &mut img,
-        (margin_left as f32, margin_top as f32),
-        (margin_left as f32, (total_height - margin_bottom) as f32),
-        black,
-    );
+// Row of a frequency tick, given as a fraction of the plot height
+fn frequency_y(plot: &Rect, fraction: f32) -> f32 {
+    (plot.bottom() as f32 - fraction * plot.height as f32 - 1.0).max(plot.y as f32)
+}
 
-    // Horizontal axis (time)
+// Draw a labelled scale along the left edge of an area, e.g. the frequency axis of the plot
+fn draw_vertical_scale(
+    img: &mut RgbaImage,
+    area: &Rect,
+    label_gap: f32,
+    style: &TextStyle,
+    ticks: &AxisTicks,
+    palette: &Palette,
+) {
+    let axis_x = area.x as f32;
     draw_line_segment_mut(
-        &mut img,
-        (margin_left as f32, (total_height - margin_bottom) as f32),
-        (
-            (total_width - margin_right) as f32,
-            (total_height - margin_bottom) as f32,
-        ),
-        black,
+        img,
+        (axis_x, area.y as f32),
+        (axis_x, area.bottom() as f32),
+        palette.axes,
     );
 
-    // Draw left frequency scale
-    draw_frequency_scale(
-        &mut img,
-        &font,
-        margin_left,
-        margin_top,
-        margin_bottom,
-        total_height,
-        sample_rate,
-        (total_height - margin_top - margin_bottom) as f32,
-    );
+    for &fraction in &ticks.minor {
+        let y_pos = frequency_y(area, fraction);
+        draw_line_segment_mut(
+            img,
+            (axis_x - MINOR_TICK_LENGTH, y_pos),
+            (axis_x, y_pos),
+            palette.axes,
+        );
+    }
 
-    // Draw bottom time scale
-    draw_time_scale(
-        &mut img,
-        &font,
-        margin_left,
-        margin_right,
-        margin_bottom,
-        total_width,
-        total_height,
-        samples.len(),
-        sample_rate,
-        num_frames,
-    );
+    for (fraction, label) in &ticks.major {
+        let y_pos = frequency_y(area, *fraction);
+        // Right-align labels next to the tick marks
+        let x_pos = axis_x - TICK_LENGTH - label_gap - style.width(label);
+        draw_text_mut(
+            img,
+            palette.text,
+            x_pos as i32,
+            (y_pos - style.center_offset()).round() as i32,
+            style.scale,
+            style.font,
+            label,
+        );
+        // Tick marks
+        draw_line_segment_mut(
+            img,
+            (axis_x - TICK_LENGTH, y_pos),
+            (axis_x, y_pos),
+            palette.axes,
+        );
+    }
+}
 
-    // Draw colorbar legend on the right
-    draw_colorbar_with_scale(
-        &mut img,
-        &font,
-        margin_top,
-        colorbar_x,
-        colorbar_width,
-        colorbar_height,
-        &gradient,
-    );
+// Blend a horizontal line of the plot halfway into the grid color, so it stays visible over any
+// colormap
+fn draw_grid_line(img: &mut RgbaImage, y: u32, plot: &Rect, color: Rgba<u8>) {
+    let weight = color[3] as f32 / 255.0 / 2.0;
+    for x in (plot.x + 1)..plot.right().min(img.width()) {
+        let pixel = img.get_pixel_mut(x, y);
+        for (channel, &target) in pixel.0.iter_mut().zip(color.0.iter()).take(3) {
+            *channel = (*channel as f32 + (target as f32 - *channel as f32) * weight).round() as u8;
+        }
+    }
+}
 
-    img
+// One-pixel border along the edges of a panel or the colorbar
+fn draw_outline(img: &mut RgbaImage, rect: &Rect, color: Rgba<u8>) {
+    let (left, right) = (rect.x as f32, rect.right() as f32);
+    let (top, bottom) = (rect.y as f32, rect.bottom() as f32);
+    draw_line_segment_mut(img, (left, top), (right, top), color);
+    draw_line_segment_mut(img, (left, bottom), (right, bottom), color);
+    draw_line_segment_mut(img, (left, top), (left, bottom), color);
+    draw_line_segment_mut(img, (right, top), (right, bottom), color);
 }
 
-// Draw left frequency scale
-fn draw_frequency_scale(
-    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    font: &Font,
-    margin_left: u32,
-    margin_top: u32,
-    margin_bottom: u32,
-    total_height: u32,
-    sample_rate: u32,
-    height_scale: f32,
+// Draw bottom time scale
+fn draw_time_scale(
+    img: &mut RgbaImage,
+    layout: &Layout,
+    style: &TextStyle,
+    ticks: &AxisTicks,
+    palette: &Palette,
 ) {
-    let freq_scale = Scale::uniform(24.0);
-    let max_freq = sample_rate as f32 / 2.0;
-
-    // Calculate frequency ticks
-    let mut last_drawn_freq = -1000.0; // Initialize to a negative value to ensure the first tick (0kHz) will be drawn
-
-    // Draw ticks starting from 0Hz
-    for i in (0..=(max_freq as i32)).step_by(1000) {
-        let freq = i as f32;
-        // Skip if frequency exceeds maximum
-        if freq > max_freq {
-            break;
-        }
-
-        let y_pos = total_height - margin_bottom - ((freq / max_freq * height_scale) as u32) - 1;
-
-        if y_pos >= margin_top && y_pos < (total_height - margin_bottom) {
-            let freq_text = format!("{:.1}kHz", freq / 1000.0);
-            draw_text_mut(
-                img,
-                Rgb([0, 0, 0]),
-                50,
-                y_pos as i32 - 12,
-                freq_scale,
-                font,
-                &freq_text,
-            );
-            // Tick marks
-            draw_line_segment_mut(
-                img,
-                (margin_left as f32 - 5.0, y_pos as f32),
-                (margin_left as f32, y_pos as f32),
-                Rgb([0, 0, 0]),
-            );
-            last_drawn_freq = freq;
-        }
+    let plot = &layout.plot;
+    let axis_y = plot.bottom() as f32;
+    let x_of = |fraction: f32| plot.x as f32 + fraction * plot.width as f32;
+    // Hang labels below the tick marks
+    let label_y = axis_y + TICK_LENGTH + layout.label_gap + style.digit_height() / 2.0
+        - style.center_offset();
+
+    for &fraction in &ticks.minor {
+        let x_pos = x_of(fraction);
+        draw_line_segment_mut(
+            img,
+            (x_pos, axis_y),
+            (x_pos, axis_y + MINOR_TICK_LENGTH),
+            palette.axes,
+        );
     }
 
-    // Check if we need to draw the highest frequency tick
-    // Only draw if the difference from the last drawn tick is >= 1kHz
-    if max_freq - last_drawn_freq >= 1000.0 {
-        // Draw highest frequency label
-        let max_freq_text = format!("{:.1}kHz", max_freq / 1000.0);
+    for (fraction, label) in &ticks.major {
+        let x_pos = x_of(*fraction);
+        // Center the label under its tick
         draw_text_mut(
             img,
-            Rgb([0, 0, 0]),
-            50,
-            margin_top as i32 - 12,
-            freq_scale,
-            font,
-            &max_freq_text,
+            palette.text,
+            (x_pos - style.width(label) / 2.0) as i32,
+            label_y.round() as i32,
+            style.scale,
+            style.font,
+            label,
         );
-        // Highest frequency tick mark
+        // Tick marks
         draw_line_segment_mut(
             img,
-            (margin_left as f32 - 5.0, margin_top as f32),
-            (margin_left as f32, margin_top as f32),
-            Rgb([0, 0, 0]),
+            (x_pos, axis_y),
+            (x_pos, axis_y + TICK_LENGTH),
+            palette.axes,
         );
     }
 }
 
-// Draw bottom time scale
-fn draw_time_scale(
-    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    font: &Font,
-    margin_left: u32,
-    margin_right: u32,
-    margin_bottom: u32,
-    total_width: u32,
-    total_height: u32,
-    samples_len: usize,
-    sample_rate: u32,
-    num_frames: usize,
+// Label the dB range at the ends of the average spectrum panel, in the row of the time labels
+fn draw_spectrum_labels(
+    img: &mut RgbaImage,
+    panel: &Rect,
+    layout: &Layout,
+    style: &TextStyle,
+    (db_min, db_max): (f32, f32),
+    palette: &Palette,
 ) {
-    let time_scale = Scale::uniform(24.0);
-    let total_time = samples_len as f32 / sample_rate as f32;
-    let num_time_ticks = (total_time / 5.0).ceil() as i32;
-
-    for i in 0..=num_time_ticks {
-        let time = i as f32 * 5.0;
-        if time > total_time {
-            break;
-        }
-
-        let x_pos = margin_left + ((time / total_time * num_frames as f32) as u32);
-        let minutes = (time as i32) / 60;
-        let seconds = (time as i32) % 60;
-
-        // Prevent x_pos from exceeding total_width - margin_right
-        if x_pos < (total_width - margin_right) {
-            draw_text_mut(
-                img,
-                Rgb([0, 0, 0]),
-                x_pos as i32 - 30,
-                (total_height - margin_bottom) as i32 + 20,
-                time_scale,
-                font,
-                &format!("{:01}:{:02}", minutes, seconds),
-            );
-            // Tick marks
-            draw_line_segment_mut(
-                img,
-                (x_pos as f32, (total_height - margin_bottom) as f32),
-                (x_pos as f32, (total_height - margin_bottom + 5) as f32),
-                Rgb([0, 0, 0]),
-            );
-        }
+    let first = format!("{:.0}dB", db_min);
+    let last = format!("{:.0}dB", db_max);
+    let axis_y = panel.bottom() as f32;
+    let label_y = axis_y + TICK_LENGTH + layout.label_gap + style.digit_height() / 2.0
+        - style.center_offset();
+    let (left, right) = (panel.x as f32, panel.right() as f32);
+    for (x_pos, label_x, label) in [
+        (left, left, &first),
+        (right, right - style.width(&last), &last),
+    ] {
+        draw_text_mut(
+            img,
+            palette.text,
+            label_x as i32,
+            label_y.round() as i32,
+            style.scale,
+            style.font,
+            label,
+        );
+        draw_line_segment_mut(
+            img,
+            (x_pos, axis_y),
+            (x_pos, axis_y + TICK_LENGTH),
+            palette.axes,
+        );
     }
 }
 
 // Draw colorbar with scale
 fn draw_colorbar_with_scale(
-    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    font: &Font,
-    margin_top: u32,
-    colorbar_x: u32,
-    colorbar_width: u32,
-    colorbar_height: u32,
+    img: &mut RgbaImage,
+    colorbar: &Rect,
+    layout: &Layout,
+    style: &TextStyle,
     gradient: &colorgrad::Gradient,
+    ticks: &[(f32, String)],
+    palette: &Palette,
 ) {
     // Draw colorbar
-    for y in 0..colorbar_height {
-        let normalized = 1.0 - (y as f32 / colorbar_height as f32);
+    for y in 0..colorbar.height {
+        let normalized = 1.0 - (y as f32 / colorbar.height as f32);
         let color = gradient.at(normalized as f64).to_rgba8();
-        for x in 0..colorbar_width {
-            img.put_pixel(
-                colorbar_x + x,
-                y + margin_top,
-                Rgb([color[0], color[1], color[2]]),
-            );
+        for x in 0..colorbar.width {
+            img.put_pixel(colorbar.x + x, colorbar.y + y, Rgba(color));
         }
     }
 
     // Draw colorbar border
-    let border_color = Rgb([0, 0, 0]);
-    // Left border
-    draw_line_segment_mut(
-        img,
-        (colorbar_x as f32, margin_top as f32),
-        (colorbar_x as f32, (margin_top + colorbar_height) as f32),
-        border_color,
-    );
-    // Right border
-    draw_line_segment_mut(
-        img,
-        ((colorbar_x + colorbar_width) as f32, margin_top as f32),
-        (
-            (colorbar_x + colorbar_width) as f32,
-            (margin_top + colorbar_height) as f32,
-        ),
-        border_color,
-    );
-    // Top border
-    draw_line_segment_mut(
-        img,
-        (colorbar_x as f32, margin_top as f32),
-        ((colorbar_x + colorbar_width) as f32, margin_top as f32),
-        border_color,
-    );
-    // Bottom border
-    draw_line_segment_mut(
-        img,
-        (colorbar_x as f32, (margin_top + colorbar_height) as f32),
-        (
-            (colorbar_x + colorbar_width) as f32,
-            (margin_top + colorbar_height) as f32,
-        ),
-        border_color,
+    draw_outline(img, colorbar, palette.axes);
+    let right = colorbar.right() as f32;
+
+    // Draw dB scale
+    for (normalized, label) in ticks {
+        let y_pos = (colorbar.bottom() as f32 - normalized * colorbar.height as f32).round();
+        draw_text_mut(
+            img,
+            palette.text,
+            (right + TICK_LENGTH + layout.label_gap) as i32,
+            (y_pos - style.center_offset()).round() as i32,
+            style.scale,
+            style.font,
+            label,
+        );
+        draw_line_segment_mut(
+            img,
+            (right, y_pos),
+            (right + TICK_LENGTH, y_pos),
+            palette.axes,
+        );
+    }
+}
+
+// Output path that selects standard output
+const STDOUT_PATH: &str = "-";
+
+// Save an image to a file (format from the extension) or stream it to stdout as PNG
+fn save_image(img: &DynamicImage, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if path != STDOUT_PATH {
+        img.save(path)?;
+        return Ok(());
+    }
+
+    let mut encoded = Vec::new();
+    img.write_to(&mut Cursor::new(&mut encoded), ImageOutputFormat::Png)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&encoded)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+// Buffered writer for a text or data output file, or stdout for "-"
+fn open_output(path: &str) -> Result<Box<dyn Write>, Box<dyn std::error::Error>> {
+    if path == STDOUT_PATH {
+        Ok(Box::new(BufWriter::new(std::io::stdout().lock())))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+// Progress bar on stderr, hidden in quiet and JSON modes
+fn progress_bar(json: bool) -> ProgressBarReporter {
+    ProgressBarReporter::new(!json && log::log_enabled!(log::Level::Info))
+}
+
+fn print_banner() {
+    log::info!("Program : {}", env!("CARGO_PKG_NAME"));
+    log::info!("Version : {}", env!("CARGO_PKG_VERSION"));
+    log::info!("Author  : {}", env!("CARGO_PKG_AUTHORS"));
+    log::info!("Built   : {}", build_time::BUILD_TIME);
+    log::info!("─────────────────────────────────────────────────");
+}
+
+// Log to stderr; informational messages are printed bare, other levels are prefixed
+fn init_logging(level: log::LevelFilter) {
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format(|buf, record| match record.level() {
+            log::Level::Info => writeln!(buf, "{}", record.args()),
+            level => writeln!(buf, "[{}] {}", level, record.args()),
+        })
+        .init();
+}
+
+// Default output path: input file stem with the given extension, optionally inside a directory
+fn default_output_path(input_path: &str, extension: &str, dir: Option<&str>) -> String {
+    let stem = if input_path == STDIN_PATH {
+        "stdin".into()
+    } else {
+        Path::new(input_path)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+    };
+    let file_name = format!("{}.{}", stem, extension);
+    match dir {
+        Some(dir) => Path::new(dir)
+            .join(file_name)
+            .to_string_lossy()
+            .into_owned(),
+        None => file_name,
+    }
+}
+
+fn run_render(args: RenderArgs, json: bool) {
+    print_banner();
+
+    let input_path = args
+        .input
+        .or(args.input_file)
+        .expect("No input file specified. Use -i option or drag-and-drop a file.");
+
+    if args.list_tracks {
+        list_tracks(&input_path).expect("Failed to read audio file");
+        return;
+    }
+
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&input_path, "png", None));
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+
+    let progress = progress_bar(json);
+    let mut stopwatch = Stopwatch::start();
+    let audio = read_audio_samples(&input_path, &args.analysis.decode, &progress)
+        .expect("Failed to read audio file");
+    let decode_ms = stopwatch.lap();
+    log::debug!(
+        "Decoded {} samples in {} channels at {} Hz in {:.0} ms",
+        audio.frames(),
+        audio.channels.len(),
+        audio.sample_rate,
+        decode_ms
     );
 
-    // Calculate dB scale
-    // Fixed dB scale range: -120dB to 0dB
-    let db_min = -120.0;
-    let db_max = 0.0;
+    let measurements = Measurements::take(&audio, &settings);
+
+    log::info!("Generating spectrogram...");
+    // An empty plot or frequency range is a problem with the input or options, not a bug
+    let spectrogram = generate_spectrogram(&audio, &measurements, &settings, &progress)
+        .unwrap_or_else(|e| {
+            log::error!("{}", e);
+            std::process::exit(1);
+        });
+    progress.finish();
+    let analysis_ms = stopwatch.lap();
+
+    save_image(&spectrogram, &output_path).expect("Failed to save spectrogram");
+    let write_ms = stopwatch.lap();
+    if output_path != STDOUT_PATH {
+        log::info!("Spectrogram saved to: {}", output_path);
+    }
 
-    let db_start = db_min;
-    let db_end = db_max;
+    if json {
+        let timings = Timings {
+            decode_ms,
+            analysis_ms,
+            write_ms,
+            total_ms: stopwatch.total(),
+        };
+        let mut run = RunReport::new(
+            &input_path,
+            &output_path,
+            audio.frames(),
+            audio.sample_rate,
+            &settings,
+            timings,
+        );
+        run.loudness = measurements.loudness;
+        run.qc = measurements.qc;
+        run.lossy = measurements.lossy;
+        run.pitch = measurements.pitch;
+        run.onsets = measurements.onsets;
+        print_json(&run);
+    }
+}
 
-    // Calculate dB values
-    let mut db_values: Vec<f32> = Vec::new();
-    let mut current_db = db_start;
-    while current_db <= db_end {
-        db_values.push(current_db);
-        current_db += 10.0;
+fn run_export(args: ExportArgs, json: bool) {
+    print_banner();
+
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&args.input, args.data_format.extension(), None));
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+
+    let progress = progress_bar(json);
+    let mut stopwatch = Stopwatch::start();
+    let audio = read_audio_samples(&args.input, &args.analysis.decode, &progress)
+        .expect("Failed to read audio file");
+    let decode_ms = stopwatch.lap();
+    let bins = bins_or_exit(&settings, audio.sample_rate);
+
+    log::info!("Computing spectrogram data...");
+    let frames = stft_magnitudes(&audio.mono(), &settings, &progress);
+    progress.finish();
+    let analysis_ms = stopwatch.lap();
+
+    export::write_spectrogram(
+        &output_path,
+        args.data_format,
+        &frames,
+        bins,
+        audio.sample_rate,
+        &settings,
+    )
+    .expect("Failed to write spectrogram data");
+    let write_ms = stopwatch.lap();
+    if output_path != STDOUT_PATH {
+        log::info!("Spectrogram data saved to: {}", output_path);
     }
 
-    // Draw dB scale
-    let db_scale = Scale::uniform(20.0);
-    let denom = db_max - db_min;
-    for &db_value in &db_values {
-        // Convert dB value to normalized value
-        let mut normalized = (db_value - db_min) / denom;
-        if !normalized.is_finite() {
-            normalized = 0.0;
+    if json {
+        let timings = Timings {
+            decode_ms,
+            analysis_ms,
+            write_ms,
+            total_ms: stopwatch.total(),
+        };
+        print_json(&RunReport::new(
+            &args.input,
+            &output_path,
+            audio.frames(),
+            audio.sample_rate,
+            &settings,
+            timings,
+        ));
+    }
+}
+
+// Optional analyses drawn alongside the spectrogram and added to the JSON result
+#[derive(Default)]
+struct Measurements {
+    loudness: Option<Loudness>,
+    qc: Option<QcReport>,
+    lossy: Option<LossyReport>,
+    pitch: Option<PitchTrack>,
+    onsets: Option<OnsetReport>,
+}
+
+impl Measurements {
+    // Run the analyses enabled in the settings, logging their summaries
+    fn take(audio: &DecodedAudio, settings: &Settings) -> Self {
+        let loudness = settings.loudness.then(|| {
+            log::info!("Measuring loudness...");
+            let loudness = loudness::measure(audio);
+            loudness::summary(&loudness)
+                .iter()
+                .for_each(|line| log::info!("{}", line));
+            loudness
+        });
+        let qc = settings.qc_markers.then(|| {
+            log::info!("Checking for clipping, DC offset and silence...");
+            let report = qc::analyze(audio);
+            qc::summary(&report)
+                .iter()
+                .for_each(|line| log::info!("{}", line));
+            report
+        });
+        let lossy = settings.lossy_check.then(|| {
+            log::info!("Checking for lossy encoding...");
+            let report = lossy::analyze(audio);
+            lossy::summary(&report)
+                .iter()
+                .for_each(|line| log::info!("{}", line));
+            report
+        });
+        let pitch = settings.pitch.then(|| {
+            log::info!("Tracking pitch...");
+            let range = (settings.pitch_min, settings.pitch_max);
+            let track = pitch::track(&audio.mono(), audio.sample_rate, range);
+            pitch::summary(&track)
+                .iter()
+                .for_each(|line| log::info!("{}", line));
+            track
+        });
+        let onsets = (settings.onsets || settings.beats).then(|| {
+            log::info!("Detecting onsets and beats...");
+            let report = onsets::analyze(&audio.mono(), audio.sample_rate);
+            onsets::summary(&report)
+                .iter()
+                .for_each(|line| log::info!("{}", line));
+            report
+        });
+        Measurements {
+            loudness,
+            qc,
+            lossy,
+            pitch,
+            onsets,
         }
-        normalized = normalized.max(0.0).min(1.0);
-        let y_pos = margin_top + ((1.0 - normalized) * colorbar_height as f32) as u32;
-
-        if y_pos >= margin_top && y_pos <= (margin_top + colorbar_height) {
-            draw_text_mut(
-                img,
-                Rgb([0, 0, 0]),
-                (colorbar_x + colorbar_width + 5) as i32,
-                y_pos as i32 - 8,
-                db_scale,
-                font,
-                &format!("{:.0}dB", db_value),
-            );
+    }
+}
 
-            draw_line_segment_mut(
-                img,
-                ((colorbar_x + colorbar_width) as f32, y_pos as f32),
-                ((colorbar_x + colorbar_width + 5) as f32, y_pos as f32),
-                border_color,
-            );
+fn run_compare(args: CompareArgs, json: bool) {
+    print_banner();
+
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&args.compared, "diff.png", None));
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+
+    let progress = progress_bar(json);
+    let read = |path: &str| {
+        read_audio_samples(path, &args.analysis.decode, &progress)
+            .expect("Failed to read audio file")
+    };
+    let (reference, compared) = (read(&args.reference), read(&args.compared));
+    if reference.sample_rate != compared.sample_rate {
+        log::error!(
+            "Sample rates differ: {} Hz and {} Hz",
+            reference.sample_rate,
+            compared.sample_rate
+        );
+        std::process::exit(1);
+    }
+    let sample_rate = reference.sample_rate;
+    let bins = bins_or_exit(&settings, sample_rate);
+    let (mut reference, mut compared) = (reference.mono(), compared.mono());
+
+    // Drop the lead-in of whichever input starts later, then compare the common length
+    let offset = if args.align {
+        let max_lag = (args.max_offset * sample_rate as f64) as usize;
+        let offset = compare::find_offset(&reference, &compared, max_lag, sample_rate);
+        log::info!(
+            "Offset: {} samples ({:.3} s)",
+            offset,
+            offset as f64 / sample_rate as f64
+        );
+        offset
+    } else {
+        0
+    };
+    let skipped = if offset > 0 {
+        &mut compared
+    } else {
+        &mut reference
+    };
+    skipped.drain(..(offset.unsigned_abs() as usize).min(skipped.len()));
+    let length = reference.len().min(compared.len());
+    reference.truncate(length);
+    compared.truncate(length);
+
+    log::info!("Comparing spectrograms...");
+    let reference_frames = stft_magnitudes(&reference, &settings, &progress);
+    let compared_frames = stft_magnitudes(&compared, &settings, &progress);
+    progress.finish();
+    let difference =
+        compare::difference_frames(&reference_frames, &compared_frames, settings.db_min);
+
+    let source = PlotSource {
+        samples: &reference,
+        sample_rate,
+        frames: &difference,
+        bins,
+    };
+    let scale = ColorScale {
+        gradient: compare::difference_gradient(),
+        range: (-args.diff_range, args.diff_range),
+        steps: compare::difference_steps(args.diff_range),
+        label: compare::difference_label,
+    };
+    let plot_image = render_plot(&difference, &source.bins, &scale, |db| db, &progress);
+    // Panels describe a single signal and are left out of the comparison
+    let figure_settings = Settings {
+        waveform: false,
+        loudness: false,
+        qc_markers: false,
+        lossy_check: false,
+        feature_overlay: false,
+        feature_panel: None,
+        spectrum: false,
+        ..settings.clone()
+    };
+    let image = draw_figure(
+        plot_image,
+        &source,
+        &scale,
+        &Measurements::default(),
+        &figure_settings,
+        &progress,
+    )
+    .expect("Failed to render difference spectrogram");
+    save_image(&image, &output_path).expect("Failed to save difference spectrogram");
+    if output_path != STDOUT_PATH {
+        log::info!("Difference spectrogram saved to: {}", output_path);
+    }
+
+    let rms_difference_db = compare::rms(&difference, &source.bins);
+    log::info!("RMS difference: {:.2} dB", rms_difference_db);
+    if json {
+        print_json(&compare::CompareReport {
+            reference: args.reference,
+            compared: args.compared,
+            output: output_path,
+            sample_rate,
+            offset_samples: offset,
+            offset_seconds: offset as f64 / sample_rate as f64,
+            frames: difference.len(),
+            rms_difference_db,
+        });
+    }
+}
+
+fn run_montage(args: MontageArgs, json: bool) {
+    print_banner();
+
+    let output_path = args.output.unwrap_or_else(|| "montage.png".to_string());
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+    if settings.no_axes {
+        log::warn!("--no-axes is ignored for montages");
+    }
+
+    let progress = progress_bar(json);
+    let inputs: Vec<DecodedAudio> = args
+        .inputs
+        .iter()
+        .map(|path| {
+            log::info!("Decoding {}...", path);
+            read_audio_samples(path, &args.analysis.decode, &progress)
+                .expect("Failed to read audio file")
+        })
+        .collect();
+    let sample_rate = inputs[0].sample_rate;
+    if let Some(audio) = inputs.iter().find(|audio| audio.sample_rate != sample_rate) {
+        log::error!(
+            "Sample rates differ: {} Hz and {} Hz",
+            sample_rate,
+            audio.sample_rate
+        );
+        std::process::exit(1);
+    }
+    let bins = bins_or_exit(&settings, sample_rate);
+
+    // Every cell covers the longest input so the time axes line up
+    let length = inputs.iter().map(DecodedAudio::frames).max().unwrap_or(0);
+    let count = inputs.len();
+    let columns = args
+        .columns
+        .unwrap_or_else(|| montage::default_columns(count))
+        .clamp(1, count);
+    let scale = ColorScale::decibels(&settings);
+    let font = load_font();
+
+    let mut cells = Vec::new();
+    for (index, (path, audio)) in args.inputs.iter().zip(&inputs).enumerate() {
+        log::info!("[{}/{}] {}", index + 1, count, path);
+        let measurements = Measurements::take(audio, &settings);
+        let mut samples = audio.mono();
+        samples.resize(length, 0.0);
+        let frames = stft_magnitudes(&samples, &settings, &progress);
+        let source = PlotSource {
+            samples: &samples,
+            sample_rate,
+            frames: &frames,
+            bins: bins.clone(),
+        };
+
+        let plot_image = render_plot(&frames, &source.bins, &scale, magnitude_to_db, &progress);
+        let cell_settings = montage::cell_settings(&settings, index, count, columns);
+        let (image, layout) = compose_figure(
+            plot_image,
+            &source,
+            &scale,
+            &measurements,
+            &cell_settings,
+            &font,
+            &progress,
+        );
+        let title = if path == STDIN_PATH {
+            "stdin".to_string()
+        } else {
+            Path::new(path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        };
+        cells.push(montage::Cell {
+            title,
+            image,
+            layout,
+        });
+    }
+    progress.finish();
+
+    let img = montage::compose(&cells, columns, &scale, &font, &settings);
+    let transparent = settings.palette().background[3] < 255;
+    save_image(&output_image(img, transparent), &output_path).expect("Failed to save montage");
+    if output_path != STDOUT_PATH {
+        log::info!("Montage saved to: {}", output_path);
+    }
+
+    if json {
+        print_json(&montage::MontageReport {
+            inputs: args.inputs,
+            output: output_path,
+            columns,
+            rows: count.div_ceil(columns),
+            sample_rate,
+            duration_seconds: length as f64 / sample_rate as f64,
+        });
+    }
+}
+
+fn run_pitch(args: PitchArgs, json: bool) {
+    print_banner();
+
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&args.input, "pitch.csv", None));
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    let progress = progress_bar(json);
+    let mut stopwatch = Stopwatch::start();
+    let audio = read_audio_samples(&args.input, &args.analysis.decode, &progress)
+        .expect("Failed to read audio file");
+    progress.finish();
+    let decode_ms = stopwatch.lap();
+
+    log::info!("Tracking pitch...");
+    let range = (settings.pitch_min, settings.pitch_max);
+    let track = pitch::track(&audio.mono(), audio.sample_rate, range);
+    let analysis_ms = stopwatch.lap();
+    pitch::write_csv(&output_path, &track).expect("Failed to write pitch data");
+    let write_ms = stopwatch.lap();
+    for line in pitch::summary(&track) {
+        log::info!("{}", line);
+    }
+    if output_path != STDOUT_PATH {
+        log::info!("Pitch data saved to: {}", output_path);
+    }
+
+    if json {
+        let timings = Timings {
+            decode_ms,
+            analysis_ms,
+            write_ms,
+            total_ms: stopwatch.total(),
+        };
+        let mut run = RunReport::new(
+            &args.input,
+            &output_path,
+            audio.frames(),
+            audio.sample_rate,
+            &settings,
+            timings,
+        );
+        run.pitch_summary = Some(PitchSummary::of(&track));
+        print_json(&run);
+    }
+}
+
+fn run_features(args: FeaturesArgs, json: bool) {
+    print_banner();
+
+    let extension = format!("features.{}", args.data_format.extension());
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&args.input, &extension, None));
+
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+
+    let progress = progress_bar(json);
+    let mut stopwatch = Stopwatch::start();
+    let audio = read_audio_samples(&args.input, &args.analysis.decode, &progress)
+        .expect("Failed to read audio file");
+    let decode_ms = stopwatch.lap();
+
+    log::info!("Computing spectral features...");
+    let samples = audio.mono();
+    let frames = stft_magnitudes(&samples, &settings, &progress);
+    let values = features::extract(&samples, &frames, audio.sample_rate, &settings);
+    progress.finish();
+    let analysis_ms = stopwatch.lap();
+
+    features::write_features(&output_path, args.data_format, &values)
+        .expect("Failed to write feature data");
+    let write_ms = stopwatch.lap();
+    if output_path != STDOUT_PATH {
+        log::info!("Feature data saved to: {}", output_path);
+    }
+
+    if json {
+        let timings = Timings {
+            decode_ms,
+            analysis_ms,
+            write_ms,
+            total_ms: stopwatch.total(),
+        };
+        print_json(&RunReport::new(
+            &args.input,
+            &output_path,
+            audio.frames(),
+            audio.sample_rate,
+            &settings,
+            timings,
+        ));
+    }
+}
+
+fn run_onsets(args: OnsetsArgs, json: bool) {
+    print_banner();
+
+    let (label, extension) = if args.beats {
+        ("beat", "beats.txt")
+    } else {
+        ("onset", "onsets.txt")
+    };
+    let output_path = args
+        .output
+        .unwrap_or_else(|| default_output_path(&args.input, extension, None));
+
+    let progress = progress_bar(json);
+    let mut stopwatch = Stopwatch::start();
+    let audio = read_audio_samples(&args.input, &args.decode, &progress)
+        .expect("Failed to read audio file");
+    progress.finish();
+    let decode_ms = stopwatch.lap();
+
+    log::info!("Detecting onsets and beats...");
+    let report = onsets::analyze(&audio.mono(), audio.sample_rate);
+    let analysis_ms = stopwatch.lap();
+    let times = if args.beats {
+        &report.beats
+    } else {
+        &report.onsets
+    };
+    onsets::write_markers(&output_path, args.marker_format, times, label)
+        .expect("Failed to write marker data");
+    let write_ms = stopwatch.lap();
+    for line in onsets::summary(&report) {
+        log::info!("{}", line);
+    }
+    if output_path != STDOUT_PATH {
+        log::info!("Marker data saved to: {}", output_path);
+    }
+
+    if json {
+        let timings = Timings {
+            decode_ms,
+            analysis_ms,
+            write_ms,
+            total_ms: stopwatch.total(),
+        };
+        // Onsets are found with their own fixed analysis frames
+        let settings = Settings {
+            fft_size: onsets::FFT_SIZE,
+            hop_size: onsets::hop_size(audio.sample_rate),
+            ..Settings::default()
+        };
+        let mut run = RunReport::new(
+            &args.input,
+            &output_path,
+            audio.frames(),
+            audio.sample_rate,
+            &settings,
+            timings,
+        );
+        run.onset_summary = Some(OnsetSummary::of(&report));
+        print_json(&run);
+    }
+}
+
+fn run_qc(args: QcArgs, json: bool) {
+    let progress = progress_bar(json);
+    let audio = read_audio_samples(&args.input, &args.decode, &progress)
+        .expect("Failed to read audio file");
+    progress.finish();
+
+    let report = qc::analyze(&audio);
+    if json {
+        print_json(&report);
+    } else {
+        for line in qc::summary(&report) {
+            println!("{}", line);
         }
     }
 }
 
-fn main() {
-    println!("Program : {}", env!("CARGO_PKG_NAME"));
-    println!("Version : {}", env!("CARGO_PKG_VERSION"));
-    println!("Author  : {}", env!("CARGO_PKG_AUTHORS"));
-    println!("Built   : {}", build_time::BUILD_TIME);
-    println!("─────────────────────────────────────────────────");
-
-    let args = Args::parse();
-    
-    let input_path = args.input.or(args.input_file)
-        .expect("No input file specified. Use -i option or drag-and-drop a file.");
+fn run_lossy(args: LossyArgs, json: bool) {
+    let progress = progress_bar(json);
+    let audio = read_audio_samples(&args.input, &args.decode, &progress)
+        .expect("Failed to read audio file");
+    progress.finish();
 
-    let output_path = args.output.unwrap_or_else(|| {
-        let input_path = std::path::Path::new(&input_path);
-        let stem = input_path.file_stem().unwrap_or_default();
-        format!("{}.png", stem.to_string_lossy())
-    });
+    let report = lossy::analyze(&audio);
+    if json {
+        print_json(&report);
+    } else {
+        for line in lossy::summary(&report) {
+            println!("{}", line);
+        }
+    }
+}
 
-    let (samples, sample_rate) =
-        read_audio_samples(&input_path).expect("Failed to read audio file");
+fn run_loudness(args: LoudnessArgs, json: bool) {
+    let progress = progress_bar(json);
+    let audio = read_audio_samples(&args.input, &args.decode, &progress)
+        .expect("Failed to read audio file");
+    progress.finish();
 
-    let fft_size = args.fft_size;
-    let hop_size = args.hop_size.unwrap_or(fft_size / 2);
+    let loudness = loudness::measure(&audio);
+    if json {
+        print_json(&loudness);
+    } else {
+        for line in loudness::summary(&loudness) {
+            println!("{}", line);
+        }
+    }
+}
+
+fn run_batch(args: BatchArgs, json: bool) {
+    print_banner();
 
-    println!("Generating spectrogram...");
-    let spectrogram = generate_spectrogram(&samples, sample_rate, fft_size, hop_size);
+    if let Some(dir) = &args.output_dir {
+        std::fs::create_dir_all(dir).expect("Failed to create output directory");
+    }
 
-    spectrogram.save(&output_path).unwrap();
-    println!("Spectrogram saved to: {}", output_path);
+    let settings = args.analysis.settings().expect("Invalid settings");
+    log::debug!("Settings: {:?}", settings);
+
+    let mut report = BatchReport {
+        results: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    for (index, input_path) in args.inputs.iter().enumerate() {
+        log::info!("[{}/{}] {}", index + 1, args.inputs.len(), input_path);
+        let output_path = default_output_path(input_path, "png", args.output_dir.as_deref());
+
+        let progress = progress_bar(json);
+        let mut stopwatch = Stopwatch::start();
+        let result =
+            read_audio_samples(input_path, &args.analysis.decode, &progress).and_then(|audio| {
+                let decode_ms = stopwatch.lap();
+                let measurements = Measurements::take(&audio, &settings);
+                let spectrogram =
+                    generate_spectrogram(&audio, &measurements, &settings, &progress)?;
+                progress.finish();
+                let analysis_ms = stopwatch.lap();
+                save_image(&spectrogram, &output_path)?;
+                let timings = Timings {
+                    decode_ms,
+                    analysis_ms,
+                    write_ms: stopwatch.lap(),
+                    total_ms: stopwatch.total(),
+                };
+                let mut run = RunReport::new(
+                    input_path,
+                    &output_path,
+                    audio.frames(),
+                    audio.sample_rate,
+                    &settings,
+                    timings,
+                );
+                run.loudness = measurements.loudness;
+                run.qc = measurements.qc;
+                run.lossy = measurements.lossy;
+                run.pitch = measurements.pitch;
+                run.onsets = measurements.onsets;
+                Ok(run)
+            });
+
+        match result {
+            Ok(run) => {
+                log::info!("Spectrogram saved to: {}", output_path);
+                report.results.push(run);
+            }
+            Err(e) => {
+                log::error!("Failed to process {}: {}", input_path, e);
+                report.failures.push(BatchFailure {
+                    input: input_path.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    log::info!(
+        "Processed {} files, {} failed",
+        args.inputs.len(),
+        report.failures.len()
+    );
+    if json {
+        print_json(&report);
+    }
+    if !report.failures.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let cli = Cli::parse_with_default_command();
+    init_logging(cli.log.level());
+    let json = cli.log.json;
+
+    match cli.command {
+        Command::Render(args) => run_render(args, json),
+        Command::Info(args) => {
+            info::print_info(&args.input, json).expect("Failed to read audio file")
+        }
+        Command::Export(args) => run_export(args, json),
+        Command::Batch(args) => run_batch(args, json),
+        Command::Loudness(args) => run_loudness(args, json),
+        Command::Qc(args) => run_qc(args, json),
+        Command::Lossy(args) => run_lossy(args, json),
+        Command::Compare(args) => run_compare(args, json),
+        Command::Montage(args) => run_montage(args, json),
+        Command::Pitch(args) => run_pitch(args, json),
+        Command::Features(args) => run_features(args, json),
+        Command::Onsets(args) => run_onsets(args, json),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequency_range_must_hold_a_bin() {
+        let settings = |freq_min: f32, freq_max: Option<f32>| Settings {
+            fft_size: 1024,
+            freq_min,
+            freq_max,
+            ..Settings::default()
+        };
+        // 8 kHz over 1024 points puts the bins 7.8125 Hz apart
+        assert_eq!(frequency_bins(&settings(0.0, None), 8000).unwrap(), 0..512);
+        assert_eq!(
+            frequency_bins(&settings(1000.0, Some(2000.0)), 8000).unwrap(),
+            128..257
+        );
+
+        let error = frequency_bins(&settings(5000.0, None), 8000).unwrap_err();
+        assert!(error.to_string().contains("Nyquist"), "{}", error);
+        let error = frequency_bins(&settings(1001.0, Some(1002.0)), 8000).unwrap_err();
+        assert!(error.to_string().contains("no FFT bin"), "{}", error);
+    }
 }
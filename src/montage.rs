@@ -0,0 +1,194 @@
+use image::{ImageBuffer, RgbaImage, imageops};
+use imageproc::drawing::draw_text_mut;
+use rusttype::Font;
+use serde::Serialize;
+
+use crate::config::Settings;
+use crate::layout::{COLORBAR_WIDTH, Element, Layout, Rect, TICK_LENGTH, TextStyle};
+use crate::{ColorScale, draw_colorbar_with_scale};
+
+// Result of rendering a montage, printed as JSON with --json
+#[derive(Serialize)]
+pub struct MontageReport {
+    pub inputs: Vec<String>,
+    pub output: String,
+    pub columns: usize,
+    pub rows: usize,
+    pub sample_rate: u32,
+    /// Length of the longest input; shorter inputs are padded with silence
+    pub duration_seconds: f64,
+}
+
+// One input rendered without margin or colorbar, titled with its file name
+pub struct Cell {
+    pub title: String,
+    pub image: RgbaImage,
+    pub layout: Layout,
+}
+
+// Columns of a near-square grid
+pub fn default_columns(count: usize) -> usize {
+    ((count as f64).sqrt().ceil() as usize).max(1)
+}
+
+// Settings for the cell at `index`: axes are shared, so only the left column keeps the
+// frequency axis and only the bottom cell of each column keeps the time axis
+pub fn cell_settings(settings: &Settings, index: usize, count: usize, columns: usize) -> Settings {
+    let mut hide = settings.hide.clone();
+    hide.push(Element::Colorbar);
+    if !index.is_multiple_of(columns) {
+        hide.push(Element::FrequencyAxis);
+    }
+    if index + columns < count {
+        hide.push(Element::TimeAxis);
+    }
+    Settings {
+        margin: 0,
+        hide,
+        ..settings.clone()
+    }
+}
+
+// Arrange the cells left to right and top to bottom with their plots aligned, and add a single
+// colorbar for all of them
+pub fn compose(
+    cells: &[Cell],
+    columns: usize,
+    scale: &ColorScale,
+    font: &Font,
+    settings: &Settings,
+) -> RgbaImage {
+    let palette = settings.palette();
+    let title_style = TextStyle::new(font, settings.font_size);
+    let colorbar_style = TextStyle::new(font, settings.font_size * 5.0 / 6.0);
+    let margin = settings.margin;
+    let label_gap = (settings.font_size * 0.4).round() as u32;
+    let panel_gap = (settings.font_size * 1.5).round() as u32;
+    let title_height = title_style.line_height().ceil() as u32 + label_gap;
+    let rows = cells.len().div_ceil(columns);
+
+    // Every plot has the same size; the cells differ in the axes and panels around them
+    let (plot_width, plot_height) = (cells[0].layout.plot.width, cells[0].layout.plot.height);
+    let content_top = |layout: &Layout| {
+        [layout.waveform, layout.loudness, layout.features]
+            .iter()
+            .flatten()
+            .map(|panel| panel.y)
+            .fold(layout.plot.y, u32::min)
+    };
+
+    // Room each cell needs around its plot, including the title above
+    let left = |column_zero: bool| {
+        cells
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i.is_multiple_of(columns) == column_zero)
+            .map(|(_, cell)| cell.layout.plot.x)
+            .max()
+            .unwrap_or(0)
+    };
+    let right = cells
+        .iter()
+        .map(|cell| cell.layout.width - cell.layout.plot.right())
+        .max()
+        .unwrap_or(0);
+    let above = cells
+        .iter()
+        .map(|cell| {
+            let layout = &cell.layout;
+            (layout.plot.y - content_top(layout) + title_height).max(layout.plot.y)
+        })
+        .max()
+        .unwrap_or(0);
+    let below: Vec<u32> = cells
+        .chunks(columns)
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.layout.height - cell.layout.plot.bottom())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    // Keep the time labels of neighbouring cells apart like those of the spectrum panel
+    let column_step = plot_width + panel_gap.max(right + left(false) + 2 * label_gap);
+    let plot_x = |column: usize| margin + left(true) + column as u32 * column_step;
+    let mut plot_y = vec![margin + above];
+    for row in 1..rows {
+        plot_y.push(plot_y[row - 1] + plot_height + below[row - 1] + label_gap + above);
+    }
+
+    let grid_right = plot_x(columns - 1) + plot_width;
+    let grid_bottom = plot_y[rows - 1] + plot_height;
+    let colorbar = (!settings.hide.contains(&Element::Colorbar)).then(|| Rect {
+        x: grid_right + panel_gap.max(right + label_gap),
+        y: plot_y[0],
+        width: COLORBAR_WIDTH,
+        height: grid_bottom - plot_y[0],
+    });
+    let colorbar_ticks = scale.ticks((grid_bottom - plot_y[0]) as f32, &colorbar_style);
+    let width = match &colorbar {
+        Some(colorbar) => {
+            let widest = colorbar_ticks
+                .iter()
+                .map(|(_, label)| colorbar_style.width(label))
+                .fold(0.0, f32::max);
+            colorbar.right() + (TICK_LENGTH + label_gap as f32 + widest).ceil() as u32 + margin
+        }
+        None => grid_right + right + margin,
+    };
+    let height = grid_bottom + below[rows - 1] + margin;
+
+    let mut img = ImageBuffer::from_pixel(width, height, palette.background);
+    for (index, cell) in cells.iter().enumerate() {
+        let layout = &cell.layout;
+        let (x, y) = (plot_x(index % columns), plot_y[index / columns]);
+        imageops::replace(
+            &mut img,
+            &cell.image,
+            (x - layout.plot.x) as i64,
+            (y - layout.plot.y) as i64,
+        );
+
+        let title = fit_title(&cell.title, &title_style, plot_width as f32);
+        let title_y = y - (layout.plot.y - content_top(layout)) - title_height;
+        draw_text_mut(
+            &mut img,
+            palette.text,
+            x as i32,
+            title_y as i32,
+            title_style.scale,
+            title_style.font,
+            &title,
+        );
+    }
+
+    if let Some(colorbar) = colorbar {
+        draw_colorbar_with_scale(
+            &mut img,
+            &colorbar,
+            &cells[0].layout,
+            &colorbar_style,
+            &scale.gradient,
+            &colorbar_ticks,
+            &palette,
+        );
+    }
+    img
+}
+
+// Shorten a title with an ellipsis until it fits the width
+fn fit_title(title: &str, style: &TextStyle, width: f32) -> String {
+    if style.width(title) <= width {
+        return title.to_string();
+    }
+    let mut chars: Vec<char> = title.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let shortened = format!("{}…", chars.iter().collect::<String>());
+        if style.width(&shortened) <= width {
+            return shortened;
+        }
+    }
+    String::new()
+}
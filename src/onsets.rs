@@ -0,0 +1,445 @@
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::cli::MarkerFormat;
+use crate::layout::Rect;
+use crate::open_output;
+use crate::stft::{Window, amplitude_scale};
+use crate::theme::Palette;
+
+// Spectra for the onset envelope, independent of the plotted FFT size
+pub const FFT_SIZE: usize = 2048;
+// One envelope value every 10 ms
+const HOP_SECONDS: f64 = 0.01;
+// Gain of the logarithmic magnitude compression, log(1 + gain * magnitude)
+const LOG_GAIN: f32 = 100.0;
+// Bins are averaged in semitone bands above this frequency, so the many high bins do not
+// drown out low onsets such as kick drums
+const LOWEST_BAND_HZ: f64 = 27.5;
+const BANDS_PER_OCTAVE: f64 = 12.0;
+
+// Adaptive peak picking (Böck et al., 2012), in seconds around the candidate frame: a peak is
+// the maximum of its neighbourhood, exceeds the local mean by DELTA and follows the previous
+// onset by at least WAIT
+const PRE_MAX: f64 = 0.03;
+const POST_MAX: f64 = 0.03;
+const PRE_AVG: f64 = 0.1;
+const POST_AVG: f64 = 0.07;
+const WAIT: f64 = 0.03;
+// Threshold above the local mean, relative to the envelope's maximum
+const DELTA: f32 = 0.07;
+
+// Tempo search range and the log-normal prior that favours moderate tempos
+const MIN_BPM: f64 = 40.0;
+const MAX_BPM: f64 = 240.0;
+const PRIOR_BPM: f64 = 120.0;
+const PRIOR_OCTAVES: f64 = 1.0;
+// Fewer onsets than this give no tempo or beats
+const MIN_TEMPO_ONSETS: usize = 4;
+// Penalty on beat intervals deviating from the period (Ellis, 2007)
+const TIGHTNESS: f64 = 100.0;
+
+// Length of the dashes of beat markers in pixels
+const DASH: u32 = 4;
+
+// Onsets from spectral flux, and the tempo and beats tracked through them
+#[derive(Serialize)]
+pub struct OnsetReport {
+    /// Onset times in seconds
+    pub onsets: Vec<f64>,
+    /// Estimated tempo, `None` with too few onsets
+    pub tempo_bpm: Option<f64>,
+    /// Beat times in seconds, empty without a tempo
+    pub beats: Vec<f64>,
+}
+
+// Counts and tempo of a report, printed by --json in place of the marker times it writes
+#[derive(Serialize)]
+pub struct OnsetSummary {
+    pub onsets: usize,
+    pub tempo_bpm: Option<f64>,
+    pub beats: usize,
+}
+
+impl OnsetSummary {
+    pub fn of(report: &OnsetReport) -> Self {
+        OnsetSummary {
+            onsets: report.onsets.len(),
+            tempo_bpm: report.tempo_bpm,
+            beats: report.beats.len(),
+        }
+    }
+}
+
+// Samples between onset envelope frames
+pub fn hop_size(sample_rate: u32) -> usize {
+    ((sample_rate as f64 * HOP_SECONDS).round() as usize).max(1)
+}
+
+pub fn analyze(samples: &[f32], sample_rate: u32) -> OnsetReport {
+    let hop = hop_size(sample_rate);
+    let frame_rate = sample_rate as f64 / hop as f64;
+    // Frames are centred on their time
+    let time_of = |frame: usize| (frame * hop) as f64 / sample_rate as f64;
+
+    let envelope = onset_envelope(samples, sample_rate, hop);
+    let onsets = pick_peaks(&envelope, frame_rate);
+    let period = (onsets.len() >= MIN_TEMPO_ONSETS)
+        .then(|| estimate_period(&envelope, frame_rate))
+        .flatten();
+    let beats = match period {
+        Some(period) => {
+            // Beats are only kept between the first and last onset, give or take half a period
+            let first = onsets[0] as f64 - period / 2.0;
+            let last = onsets[onsets.len() - 1] as f64 + period / 2.0;
+            track_beats(&envelope, period)
+                .into_iter()
+                .filter(|&beat| (first..=last).contains(&(beat as f64)))
+                .map(time_of)
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    OnsetReport {
+        onsets: onsets.into_iter().map(time_of).collect(),
+        tempo_bpm: period.map(|period| 60.0 * frame_rate / period),
+        beats,
+    }
+}
+
+// Spectral flux of the log-compressed band magnitudes: the summed increases since the previous
+// frame, normalized to a maximum of 1
+fn onset_envelope(samples: &[f32], sample_rate: u32, hop: usize) -> Vec<f32> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    // Half a window of silence on either side centres the first frame on the start of the
+    // file, where the window would otherwise hide a hit
+    let mut padded = vec![0.0f32; FFT_SIZE / 2];
+    padded.extend_from_slice(samples);
+    padded.resize(padded.len() + FFT_SIZE / 2, 0.0);
+    let window = Window::Hann.coefficients(FFT_SIZE);
+    let scale = amplitude_scale(&window);
+
+    // Band of every bin, numbered from zero without gaps
+    let bin_width = sample_rate as f64 / FFT_SIZE as f64;
+    let mut band_of = Vec::with_capacity(FFT_SIZE / 2);
+    let mut bands = 0;
+    let mut last_band = None;
+    for bin in 0..FFT_SIZE / 2 {
+        let frequency = (bin as f64 * bin_width).max(LOWEST_BAND_HZ);
+        let band = (BANDS_PER_OCTAVE * (frequency / LOWEST_BAND_HZ).log2()).round() as usize;
+        if last_band != Some(band) {
+            last_band = Some(band);
+            bands += 1;
+        }
+        band_of.push(bands - 1);
+    }
+    let mut band_size = vec![0.0f32; bands];
+    band_of.iter().for_each(|&band| band_size[band] += 1.0);
+
+    // The first frame is compared against silence, so a file starting on a hit has an onset
+    let mut previous = vec![0.0f32; bands];
+    let mut envelope: Vec<f32> = (0..=(padded.len() - FFT_SIZE) / hop)
+        .map(|i| {
+            let start = i * hop;
+            let spectrum = crate::compute_spectrum(&padded[start..start + FFT_SIZE], &window);
+            let mut compressed = vec![0.0f32; bands];
+            for (&band, &magnitude) in band_of.iter().zip(&spectrum) {
+                compressed[band] += magnitude;
+            }
+            for (value, size) in compressed.iter_mut().zip(&band_size) {
+                *value = (LOG_GAIN * *value / size * scale).ln_1p();
+            }
+            let flux = compressed
+                .iter()
+                .zip(&previous)
+                .map(|(&m, &p)| (m - p).max(0.0))
+                .sum();
+            previous = compressed;
+            flux
+        })
+        .collect();
+
+    // A file starting mid-sound jumps from silence in the first frame; it is capped at the
+    // maximum of the other frames so it cannot flatten the rest of the envelope
+    let rest = if envelope.len() > 1 {
+        &envelope[1..]
+    } else {
+        &envelope[..]
+    };
+    let max = rest.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        envelope
+            .iter_mut()
+            .for_each(|value| *value = (*value / max).min(1.0));
+    }
+    envelope
+}
+
+// Frames of the envelope's peaks that stand out from their surroundings
+fn pick_peaks(envelope: &[f32], frame_rate: f64) -> Vec<usize> {
+    let frames = |seconds: f64| (seconds * frame_rate).round() as usize;
+    let (pre_max, post_max) = (frames(PRE_MAX), frames(POST_MAX));
+    let (pre_avg, post_avg) = (frames(PRE_AVG), frames(POST_AVG));
+    let wait = frames(WAIT);
+
+    let around = |i: usize, before: usize, after: usize| {
+        &envelope[i.saturating_sub(before)..(i + after + 1).min(envelope.len())]
+    };
+    let mut onsets: Vec<usize> = Vec::new();
+    for (i, &value) in envelope.iter().enumerate() {
+        let neighbourhood = around(i, pre_max, post_max);
+        let average = around(i, pre_avg, post_avg);
+        let mean = average.iter().sum::<f32>() / average.len() as f32;
+        if value > 0.0
+            && neighbourhood.iter().all(|&other| other <= value)
+            && value >= mean + DELTA
+            && onsets.last().is_none_or(|&last| i > last + wait)
+        {
+            onsets.push(i);
+        }
+    }
+    onsets
+}
+
+// Beat period in frames at the strongest autocorrelation of the envelope, weighted towards
+// PRIOR_BPM to settle octave ambiguities
+fn estimate_period(envelope: &[f32], frame_rate: f64) -> Option<f64> {
+    let min_lag = ((60.0 * frame_rate / MAX_BPM).floor() as usize).max(1);
+    let max_lag = (60.0 * frame_rate / MIN_BPM).ceil() as usize;
+    if envelope.len() <= max_lag + 1 {
+        return None;
+    }
+
+    let mean = envelope.iter().sum::<f32>() / envelope.len() as f32;
+    let centred: Vec<f64> = envelope.iter().map(|&v| (v - mean) as f64).collect();
+    let autocorrelation = |lag: usize| {
+        let sum: f64 = centred
+            .iter()
+            .zip(&centred[lag..])
+            .map(|(a, b)| a * b)
+            .sum();
+        sum / (centred.len() - lag) as f64
+    };
+    let weighted: Vec<f64> = (min_lag - 1..=max_lag + 1)
+        .map(|lag| {
+            let bpm = 60.0 * frame_rate / lag as f64;
+            let octaves = (bpm / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+            autocorrelation(lag) * (-0.5 * octaves * octaves).exp()
+        })
+        .collect();
+
+    // Index 0 is the lag below the range, kept for the interpolation
+    let index = (1..weighted.len() - 1).max_by(|&a, &b| weighted[a].total_cmp(&weighted[b]))?;
+    if weighted[index] <= 0.0 {
+        return None;
+    }
+    // Parabolic interpolation around the peak
+    let (before, at, after) = (weighted[index - 1], weighted[index], weighted[index + 1]);
+    let curvature = before - 2.0 * at + after;
+    let shift = if curvature < 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some((min_lag - 1 + index) as f64 + shift)
+}
+
+// Beat frames by dynamic programming: each beat maximizes the envelope it lands on minus a
+// penalty for intervals deviating from the period
+fn track_beats(envelope: &[f32], period: f64) -> Vec<usize> {
+    // The envelope is scaled to unit standard deviation so TIGHTNESS has a fixed meaning
+    let mean = envelope.iter().map(|&v| v as f64).sum::<f64>() / envelope.len() as f64;
+    let variance = envelope
+        .iter()
+        .map(|&v| (v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / envelope.len() as f64;
+    let spread = variance.sqrt().max(f64::EPSILON);
+
+    let shortest = (period / 2.0).round().max(1.0) as usize;
+    let longest = (2.0 * period).round() as usize;
+    let mut score = vec![0.0f64; envelope.len()];
+    let mut backlink: Vec<Option<usize>> = vec![None; envelope.len()];
+    for i in 0..envelope.len() {
+        let local = envelope[i] as f64 / spread;
+        let candidates = if i >= shortest {
+            i.saturating_sub(longest)..i - shortest + 1
+        } else {
+            0..0
+        };
+        let best = candidates
+            .map(|previous| {
+                let penalty = ((i - previous) as f64 / period).ln().powi(2);
+                (previous, score[previous] - TIGHTNESS * penalty)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((previous, value)) if value > 0.0 => {
+                score[i] = local + value;
+                backlink[i] = Some(previous);
+            }
+            _ => score[i] = local,
+        }
+    }
+
+    // The last beat is the last local maximum of the score that reaches half their median
+    let mut maxima: Vec<usize> = (1..score.len().saturating_sub(1))
+        .filter(|&i| score[i] > score[i - 1] && score[i] >= score[i + 1])
+        .collect();
+    let mut values: Vec<f64> = maxima.iter().map(|&i| score[i]).collect();
+    values.sort_by(f64::total_cmp);
+    let Some(&median) = values.get(values.len() / 2) else {
+        return Vec::new();
+    };
+    maxima.retain(|&i| score[i] >= median / 2.0);
+
+    let mut beats = Vec::new();
+    let mut current = maxima.last().copied();
+    while let Some(beat) = current {
+        beats.push(beat);
+        current = backlink[beat];
+    }
+    beats.reverse();
+    beats
+}
+
+// Vertical lines over the plot: solid for onsets, dashed for beats
+pub fn draw_markers(
+    img: &mut RgbaImage,
+    plot: &Rect,
+    report: &OnsetReport,
+    duration: f64,
+    (onsets, beats): (bool, bool),
+    palette: &Palette,
+) {
+    let mut line = |time: f64, color: Rgba<u8>, dashed: bool| {
+        let x = (time / duration * plot.width as f64) as u32;
+        if x >= plot.width {
+            return;
+        }
+        for y in plot.y..plot.bottom() {
+            if !dashed || ((y - plot.y) / DASH).is_multiple_of(2) {
+                img.put_pixel(plot.x + x, y, color);
+            }
+        }
+    };
+
+    if onsets {
+        for &time in &report.onsets {
+            line(time, palette.onset, false);
+        }
+    }
+    // Beats are drawn last so they stay visible where they coincide with onsets
+    if beats {
+        for &time in &report.beats {
+            line(time, palette.beat, true);
+        }
+    }
+}
+
+// Write onset or beat times as one timestamp per line, or as an Audacity label track
+pub fn write_markers(
+    path: &str,
+    format: MarkerFormat,
+    times: &[f64],
+    label: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = open_output(path)?;
+    for (index, time) in times.iter().enumerate() {
+        match format {
+            MarkerFormat::Text => writeln!(writer, "{:.6}", time)?,
+            // Point labels: start and end coincide
+            MarkerFormat::Labels => {
+                writeln!(writer, "{:.6}\t{:.6}\t{} {}", time, time, label, index + 1)?
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Human-readable summary of the onsets and the tempo
+pub fn summary(report: &OnsetReport) -> Vec<String> {
+    let mut lines = vec![format!("Onsets: {}", report.onsets.len())];
+    match report.tempo_bpm {
+        Some(tempo) => lines.push(format!(
+            "Tempo: {:.1} BPM, {} beats",
+            tempo,
+            report.beats.len()
+        )),
+        None => lines.push("Tempo: too few onsets to estimate".to_string()),
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::Noise;
+
+    const SAMPLE_RATE: u32 = 44100;
+
+    // Decaying noise bursts every `interval` seconds, the first at `start`
+    fn click_track(start: f64, interval: f64, seconds: f64) -> Vec<f32> {
+        let mut samples = vec![0.0f32; (seconds * SAMPLE_RATE as f64) as usize];
+        let mut noise = Noise::new();
+        let mut time = start;
+        while time < seconds - 0.05 {
+            let first = (time * SAMPLE_RATE as f64) as usize;
+            for ((i, sample), noise) in samples[first..first + 441]
+                .iter_mut()
+                .enumerate()
+                .zip(&mut noise)
+            {
+                *sample = noise * (-(i as f32) / 100.0).exp();
+            }
+            time += interval;
+        }
+        samples
+    }
+
+    #[test]
+    fn finds_tempo_and_beats_of_a_120_bpm_click_track() {
+        let report = analyze(&click_track(1.0, 0.5, 11.0), SAMPLE_RATE);
+
+        assert_eq!(report.onsets.len(), 20);
+        for (onset, click) in report
+            .onsets
+            .iter()
+            .zip((0..).map(|i| 1.0 + 0.5 * i as f64))
+        {
+            assert!(
+                (onset - click).abs() < 0.02,
+                "onset {} for click {}",
+                onset,
+                click
+            );
+        }
+        let tempo = report.tempo_bpm.expect("No tempo");
+        assert!((tempo - 120.0).abs() < 1.0, "tempo {}", tempo);
+        assert!(report.beats.len() >= 19, "{} beats", report.beats.len());
+        for pair in report.beats.windows(2) {
+            assert!((pair[1] - pair[0] - 0.5).abs() < 0.02, "beats {:?}", pair);
+        }
+    }
+
+    #[test]
+    fn reports_an_onset_at_the_start_of_the_file() {
+        let report = analyze(&click_track(0.0, 0.5, 5.0), SAMPLE_RATE);
+        let first = report.onsets.first().expect("No onsets");
+        assert!(*first < 0.03, "first onset {}", first);
+        assert!(report.beats.first().is_some_and(|&beat| beat < 0.03));
+    }
+
+    #[test]
+    fn silence_has_no_onsets_or_tempo() {
+        let report = analyze(&vec![0.0; SAMPLE_RATE as usize * 5], SAMPLE_RATE);
+        assert!(report.onsets.is_empty());
+        assert!(report.tempo_bpm.is_none());
+        assert!(report.beats.is_empty());
+    }
+}
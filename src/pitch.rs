@@ -0,0 +1,269 @@
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+use rustfft::{FftPlanner, num_complex::Complex};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::layout::Rect;
+use crate::open_output;
+use crate::theme::Palette;
+
+// One pitch estimate every 10 ms
+const STEP_SECONDS: f64 = 0.01;
+// YIN absolute threshold on the cumulative mean normalized difference
+const THRESHOLD: f32 = 0.15;
+// Frames quieter than this are unvoiced
+const SILENCE_DBFS: f32 = -60.0;
+
+// Fundamental frequency estimated with YIN (de Cheveigné and Kawahara, 2002)
+#[derive(Serialize)]
+pub struct PitchTrack {
+    pub points: Vec<PitchPoint>,
+}
+
+#[derive(Serialize)]
+pub struct PitchPoint {
+    /// Centre of the analysis window
+    pub time_s: f64,
+    /// Estimated F0, `None` for unvoiced or silent frames
+    pub frequency_hz: Option<f32>,
+    /// One minus the normalized difference at the chosen period, 0 for silence
+    pub confidence: f32,
+}
+
+// Voicing and F0 statistics of a track, reported by --json instead of every estimate
+#[derive(Serialize)]
+pub struct PitchSummary {
+    pub frames: usize,
+    pub voiced_frames: usize,
+    /// F0 statistics over the voiced frames, `None` without any
+    pub median_hz: Option<f32>,
+    pub min_hz: Option<f32>,
+    pub max_hz: Option<f32>,
+}
+
+impl PitchSummary {
+    pub fn of(track: &PitchTrack) -> Self {
+        let mut voiced: Vec<f32> = track
+            .points
+            .iter()
+            .filter_map(|point| point.frequency_hz)
+            .collect();
+        voiced.sort_by(f32::total_cmp);
+        PitchSummary {
+            frames: track.points.len(),
+            voiced_frames: voiced.len(),
+            median_hz: voiced.get(voiced.len() / 2).copied(),
+            min_hz: voiced.first().copied(),
+            max_hz: voiced.last().copied(),
+        }
+    }
+}
+
+pub fn track(samples: &[f32], sample_rate: u32, (min_hz, max_hz): (f32, f32)) -> PitchTrack {
+    let rate = sample_rate as f32;
+    let min_period = ((rate / max_hz).floor() as usize).max(2);
+    let max_period = (rate / min_hz).ceil() as usize;
+    // The integration window spans the longest period
+    let window = max_period;
+    let span = window + max_period + 1;
+    let step = ((sample_rate as f64 * STEP_SECONDS).round() as usize).max(1);
+    if samples.len() < span || min_period >= max_period {
+        return PitchTrack { points: Vec::new() };
+    }
+
+    let size = span.next_power_of_two();
+    let mut planner = FftPlanner::new();
+    let forward = planner.plan_fft_forward(size);
+    let inverse = planner.plan_fft_inverse(size);
+
+    // Running sums of squares give the energy of any window in constant time
+    let mut energy = vec![0.0f64; samples.len() + 1];
+    for (i, &s) in samples.iter().enumerate() {
+        energy[i + 1] = energy[i] + (s as f64).powi(2);
+    }
+
+    let points = (0..=(samples.len() - span) / step)
+        .map(|i| {
+            let start = i * step;
+            let time_s = (start + window / 2) as f64 / sample_rate as f64;
+            let frame = &samples[start..start + span];
+
+            let window_energy = energy[start + window] - energy[start];
+            let rms_db = 10.0 * (window_energy / window as f64).max(1e-20).log10() as f32;
+            if rms_db < SILENCE_DBFS {
+                return PitchPoint {
+                    time_s,
+                    frequency_hz: None,
+                    confidence: 0.0,
+                };
+            }
+
+            // Autocorrelation of the window against the frame at every lag, via FFT
+            let spectrum = |signal: &[f32]| {
+                let mut buffer: Vec<Complex<f32>> =
+                    signal.iter().map(|&s| Complex::new(s, 0.0)).collect();
+                buffer.resize(size, Complex::new(0.0, 0.0));
+                forward.process(&mut buffer);
+                buffer
+            };
+            let head = spectrum(&frame[..window]);
+            let mut correlation: Vec<Complex<f32>> = spectrum(frame)
+                .iter()
+                .zip(&head)
+                .map(|(f, h)| f * h.conj())
+                .collect();
+            inverse.process(&mut correlation);
+
+            // Cumulative mean normalized difference function
+            let mut normalized = vec![1.0f32; max_period + 1];
+            let mut running = 0.0f64;
+            for lag in 1..=max_period {
+                let shifted = energy[start + lag + window] - energy[start + lag];
+                let autocorrelation = correlation[lag].re as f64 / size as f64;
+                let difference = (window_energy + shifted - 2.0 * autocorrelation).max(0.0);
+                running += difference;
+                if running > 0.0 {
+                    normalized[lag] = (difference * lag as f64 / running) as f32;
+                }
+            }
+
+            // First dip below the threshold, followed down to its minimum, else the global minimum
+            let candidates = &normalized[min_period..max_period];
+            let lag = match candidates.iter().position(|&d| d < THRESHOLD) {
+                Some(offset) => {
+                    let mut lag = min_period + offset;
+                    while lag + 1 < max_period && normalized[lag + 1] < normalized[lag] {
+                        lag += 1;
+                    }
+                    lag
+                }
+                None => {
+                    let offset = candidates
+                        .iter()
+                        .enumerate()
+                        .min_by(|a, b| a.1.total_cmp(b.1))
+                        .map_or(0, |(offset, _)| offset);
+                    min_period + offset
+                }
+            };
+
+            // Parabolic interpolation around the chosen lag
+            let (before, at, after) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+            let curvature = before - 2.0 * at + after;
+            let shift = if curvature > 0.0 {
+                (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+            } else {
+                0.0
+            };
+            let confidence = (1.0 - at).clamp(0.0, 1.0);
+            PitchPoint {
+                time_s,
+                frequency_hz: (at < THRESHOLD).then(|| rate / (lag as f32 + shift)),
+                confidence,
+            }
+        })
+        .collect();
+
+    PitchTrack { points }
+}
+
+// Draw the contour of consecutive voiced estimates over the plot
+pub fn draw_contour(
+    img: &mut RgbaImage,
+    plot: &Rect,
+    track: &PitchTrack,
+    duration: f64,
+    (min_freq, max_freq): (f32, f32),
+    palette: &Palette,
+) {
+    let position = |point: &PitchPoint| {
+        let frequency = point.frequency_hz?;
+        let fraction = (frequency - min_freq) / (max_freq - min_freq);
+        (0.0..=1.0).contains(&fraction).then(|| {
+            let x = plot.x as f32 + (point.time_s / duration * plot.width as f64) as f32;
+            (x, crate::frequency_y(plot, fraction))
+        })
+    };
+
+    for pair in track.points.windows(2) {
+        if let (Some(from), Some(to)) = (position(&pair[0]), position(&pair[1])) {
+            // Doubled a pixel lower so the contour stays visible over the bright fundamental
+            // it follows
+            for offset in [0.0, 1.0] {
+                draw_line_segment_mut(
+                    img,
+                    (from.0, from.1 + offset),
+                    (to.0, to.1 + offset),
+                    palette.pitch,
+                );
+            }
+        }
+    }
+}
+
+// Write one row per estimate; unvoiced frames have an empty frequency
+pub fn write_csv(path: &str, track: &PitchTrack) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = open_output(path)?;
+    writeln!(writer, "time_s,frequency_hz,confidence")?;
+    for point in &track.points {
+        write!(writer, "{:.6},", point.time_s)?;
+        if let Some(frequency) = point.frequency_hz {
+            write!(writer, "{:.2}", frequency)?;
+        }
+        writeln!(writer, ",{:.3}", point.confidence)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Human-readable summary of the voiced frames
+pub fn summary(track: &PitchTrack) -> Vec<String> {
+    let stats = PitchSummary::of(track);
+    let mut lines = vec![format!(
+        "Voiced frames: {} of {}",
+        stats.voiced_frames, stats.frames
+    )];
+    if let (Some(median), Some(low), Some(high)) = (stats.median_hz, stats.min_hz, stats.max_hz) {
+        lines.push(format!(
+            "F0: median {:.1} Hz, range {:.1} - {:.1} Hz",
+            median, low, high
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::sine;
+
+    #[test]
+    fn tracks_a_440_hz_sine() {
+        let track = track(&sine(440.0, 0.5, 1.0, 44100), 44100, (50.0, 1000.0));
+        assert!(!track.points.is_empty());
+        for point in &track.points {
+            let frequency = point.frequency_hz.expect("Unvoiced frame in a steady sine");
+            assert!((frequency - 440.0).abs() < 1.0, "frequency {}", frequency);
+            assert!(point.confidence > 0.9, "confidence {}", point.confidence);
+        }
+    }
+
+    #[test]
+    fn silence_is_unvoiced() {
+        let track = track(&vec![0.0; 44100], 44100, (50.0, 1000.0));
+        assert!(!track.points.is_empty());
+        assert!(
+            track
+                .points
+                .iter()
+                .all(|point| point.frequency_hz.is_none())
+        );
+    }
+
+    #[test]
+    fn too_short_input_has_no_points() {
+        let track = track(&sine(440.0, 0.5, 0.01, 44100), 44100, (50.0, 1000.0));
+        assert!(track.points.is_empty());
+    }
+}
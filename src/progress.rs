@@ -0,0 +1,90 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::cell::Cell;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    /// Decoding the input; units are audio frames (samples per channel)
+    Decode,
+    /// Computing STFT frames
+    Analysis,
+    /// Coloring the plot; units are plot columns
+    Render,
+    /// Drawing the axes, panels and overlays around the plot; units are drawing steps
+    Draw,
+}
+
+impl Stage {
+    fn label(self) -> &'static str {
+        match self {
+            Stage::Decode => "Decoding",
+            Stage::Analysis => "Analysing",
+            Stage::Render => "Rendering",
+            Stage::Draw => "Drawing",
+        }
+    }
+}
+
+// Receives progress of long-running stages; `total` is 0 when the length is unknown.
+// Any `Fn(Stage, u64, u64)` closure can be used, e.g. to drive a GUI progress indicator.
+pub trait Progress {
+    fn update(&self, stage: Stage, done: u64, total: u64);
+}
+
+impl<F: Fn(Stage, u64, u64)> Progress for F {
+    fn update(&self, stage: Stage, done: u64, total: u64) {
+        self(stage, done, total)
+    }
+}
+
+// Progress bar drawn on stderr, restarted for each stage
+pub struct ProgressBarReporter {
+    bar: ProgressBar,
+    stage: Cell<Option<Stage>>,
+}
+
+impl ProgressBarReporter {
+    pub fn new(visible: bool) -> Self {
+        let bar = if visible {
+            ProgressBar::new(0)
+        } else {
+            ProgressBar::hidden()
+        };
+        ProgressBarReporter {
+            bar,
+            stage: Cell::new(None),
+        }
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+impl Progress for ProgressBarReporter {
+    fn update(&self, stage: Stage, done: u64, total: u64) {
+        if self.stage.get() != Some(stage) {
+            self.stage.set(Some(stage));
+            let template = if total > 0 {
+                "{msg:>10} [{bar:40}] {percent:>3}% ({eta} left)"
+            } else {
+                "{msg:>10} {spinner} {pos} frames"
+            };
+            self.bar.set_style(
+                ProgressStyle::with_template(template)
+                    .expect("Invalid progress template")
+                    .progress_chars("=> "),
+            );
+            self.bar.set_message(stage.label());
+            self.bar.reset();
+        }
+
+        self.bar.set_length(total);
+        self.bar.set_position(done);
+    }
+}
+
+impl Drop for ProgressBarReporter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
@@ -0,0 +1,247 @@
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+
+use crate::audio::DecodedAudio;
+use crate::layout::Rect;
+use crate::theme::Palette;
+
+// Clipping holds samples at the channel's positive or negative extreme; extremes further than
+// this below full scale (-1 dBFS) are not clipping. 8-bit and µ-law or A-law sources reach
+// only about -0.2 dBFS on the positive side.
+const MIN_CLIP_LEVEL: f32 = 0.891;
+// Relative tolerance for samples counted as at the extreme
+const CLIP_TOLERANCE: f32 = 1e-6;
+// Shortest run of consecutive samples at the extreme that counts as clipping
+const MIN_CLIP_RUN: usize = 3;
+// Samples below one 16-bit step count as digital silence
+const SILENCE_LEVEL: f32 = 1.0 / 65536.0;
+const MIN_SILENCE_SECONDS: f64 = 0.5;
+
+// Height of the marker strip along the bottom edge of the plot
+const MARKER_HEIGHT: u32 = 6;
+
+#[derive(Serialize)]
+pub struct QcReport {
+    pub channels: Vec<ChannelQc>,
+    pub clipping: Vec<ClipRun>,
+    /// Stretches where every channel is silent
+    pub silence: Vec<Segment>,
+}
+
+#[derive(Serialize)]
+pub struct ChannelQc {
+    pub channel: usize,
+    pub sample_peak_dbfs: Option<f64>,
+    /// Mean sample value
+    pub dc_offset: f64,
+    pub clipped_samples: usize,
+}
+
+#[derive(Serialize)]
+pub struct ClipRun {
+    pub channel: usize,
+    pub start_s: f64,
+    pub samples: usize,
+}
+
+#[derive(Serialize)]
+pub struct Segment {
+    pub start_s: f64,
+    pub end_s: f64,
+}
+
+pub fn analyze(audio: &DecodedAudio) -> QcReport {
+    let seconds = |sample: usize| sample as f64 / audio.sample_rate as f64;
+    let mut channels = Vec::new();
+    let mut clipping = Vec::new();
+
+    for (index, samples) in audio.channels.iter().enumerate() {
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let sum: f64 = samples.iter().map(|&s| s as f64).sum();
+
+        // Extremes of either polarity, capped at full scale so overshoots count from there
+        let high = samples.iter().copied().fold(0.0f32, f32::max).min(1.0);
+        let low = samples.iter().copied().fold(0.0f32, f32::min).max(-1.0);
+        let at_extreme = |s: f32| {
+            (high >= MIN_CLIP_LEVEL && s >= high * (1.0 - CLIP_TOLERANCE))
+                || (-low >= MIN_CLIP_LEVEL && s <= low * (1.0 - CLIP_TOLERANCE))
+        };
+
+        let mut clipped_samples = 0;
+        for (start, length) in runs(samples.iter().map(|&s| at_extreme(s))) {
+            // Single samples at the extreme are ordinary peaks
+            if length >= MIN_CLIP_RUN {
+                clipped_samples += length;
+                clipping.push(ClipRun {
+                    channel: index + 1,
+                    start_s: seconds(start),
+                    samples: length,
+                });
+            }
+        }
+
+        channels.push(ChannelQc {
+            channel: index + 1,
+            sample_peak_dbfs: (peak > 0.0).then(|| 20.0 * (peak as f64).log10()),
+            dc_offset: sum / samples.len().max(1) as f64,
+            clipped_samples,
+        });
+    }
+    clipping.sort_by(|a, b| a.start_s.total_cmp(&b.start_s));
+
+    let min_silence = (MIN_SILENCE_SECONDS * audio.sample_rate as f64) as usize;
+    let silent = (0..audio.frames()).map(|i| {
+        audio
+            .channels
+            .iter()
+            .all(|channel| channel[i].abs() < SILENCE_LEVEL)
+    });
+    let silence = runs(silent)
+        .filter(|&(_, length)| length >= min_silence)
+        .map(|(start, length)| Segment {
+            start_s: seconds(start),
+            end_s: seconds(start + length),
+        })
+        .collect();
+
+    QcReport {
+        channels,
+        clipping,
+        silence,
+    }
+}
+
+// Start and length of every run of `true` values
+fn runs(flags: impl Iterator<Item = bool>) -> impl Iterator<Item = (usize, usize)> {
+    let mut flags = flags.enumerate().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = flags.find(|&(_, flag)| flag)?;
+        let mut length = 1;
+        while flags.next_if(|&(_, flag)| flag).is_some() {
+            length += 1;
+        }
+        Some((start, length))
+    })
+}
+
+// Mark silent stretches and clipped runs in a strip along the bottom edge of the plot
+pub fn draw_markers(
+    img: &mut RgbaImage,
+    plot: &Rect,
+    report: &QcReport,
+    duration: f64,
+    palette: &Palette,
+) {
+    let column = |time: f64| (time / duration * plot.width as f64) as u32;
+    let mut fill = |first: u32, last: u32, color: Rgba<u8>| {
+        for x in first..=last.min(plot.width.saturating_sub(1)) {
+            for y in plot.bottom().saturating_sub(MARKER_HEIGHT)..plot.bottom() {
+                img.put_pixel(plot.x + x, y, color);
+            }
+        }
+    };
+
+    for segment in &report.silence {
+        fill(
+            column(segment.start_s),
+            column(segment.end_s),
+            palette.qc_silence,
+        );
+    }
+    // Clipping is drawn last so short runs stay visible inside silent stretches
+    for run in &report.clipping {
+        fill(
+            column(run.start_s),
+            column(run.start_s),
+            palette.qc_clipping,
+        );
+    }
+}
+
+// Peak and DC offset of every channel, then each clipped run and silent segment
+pub fn summary(report: &QcReport) -> Vec<String> {
+    let mut lines = Vec::new();
+    for channel in &report.channels {
+        let peak = match channel.sample_peak_dbfs {
+            Some(peak) => format!("{:.1} dBFS", peak),
+            None => "-inf dBFS".to_string(),
+        };
+        lines.push(format!(
+            "Channel {}: sample peak {}, DC offset {:+.6}, {} clipped samples",
+            channel.channel, peak, channel.dc_offset, channel.clipped_samples
+        ));
+    }
+
+    lines.push(format!("Clipped runs: {}", report.clipping.len()));
+    for run in &report.clipping {
+        lines.push(format!(
+            "  {:.3}s channel {}, {} samples",
+            run.start_s, run.channel, run.samples
+        ));
+    }
+
+    lines.push(format!("Silent segments: {}", report.silence.len()));
+    for segment in &report.silence {
+        lines.push(format!("  {:.3}s - {:.3}s", segment.start_s, segment.end_s));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_signals::{mono, sine};
+
+    #[test]
+    fn runs_at_the_channels_own_extremes_are_clipping() {
+        // An 8-bit source clips one step short of full scale on the positive side
+        let samples = sine(100.0, 1.2, 0.1, 8000)
+            .into_iter()
+            .map(|s| s.clamp(-1.0, 127.0 / 128.0))
+            .collect();
+        let report = analyze(&mono(samples, 8000));
+        // One run on each half of the ten cycles
+        assert_eq!(report.clipping.len(), 20);
+        assert!(report.clipping.iter().all(|run| run.channel == 1));
+        let clipped: usize = report.clipping.iter().map(|run| run.samples).sum();
+        assert_eq!(report.channels[0].clipped_samples, clipped);
+    }
+
+    #[test]
+    fn runs_shorter_than_the_minimum_are_peaks() {
+        let mut samples = sine(100.0, 0.5, 0.1, 8000);
+        samples[100..100 + MIN_CLIP_RUN - 1].fill(1.0);
+        let report = analyze(&mono(samples.clone(), 8000));
+        assert!(report.clipping.is_empty());
+
+        samples[300..300 + MIN_CLIP_RUN].fill(1.0);
+        let report = analyze(&mono(samples, 8000));
+        assert_eq!(report.clipping.len(), 1);
+        assert_eq!(report.clipping[0].start_s, 300.0 / 8000.0);
+        assert_eq!(report.clipping[0].samples, MIN_CLIP_RUN);
+    }
+
+    #[test]
+    fn sine_just_below_full_scale_is_not_clipped() {
+        let report = analyze(&mono(
+            sine(1000.0, 10f64.powf(-0.01 / 20.0), 1.0, 48000),
+            48000,
+        ));
+        assert!(report.clipping.is_empty());
+        let peak = report.channels[0].sample_peak_dbfs.unwrap();
+        assert!((peak + 0.01).abs() < 1e-3, "peak {}", peak);
+    }
+
+    #[test]
+    fn lists_silence_longer_than_the_minimum() {
+        let tone = sine(100.0, 0.5, 0.5, 8000);
+        let samples = [&tone[..], &[0.0; 4800], &tone, &[0.0; 2400], &tone].concat();
+        let report = analyze(&mono(samples, 8000));
+        // The 0.3 s gap is too short to list
+        assert_eq!(report.silence.len(), 1);
+        // Give or take the zero crossing the tone starts on
+        let segment = &report.silence[0];
+        assert!((segment.start_s - 0.5).abs() < 1e-3, "{}", segment.start_s);
+        assert!((segment.end_s - 1.1).abs() < 1e-3, "{}", segment.end_s);
+    }
+}
@@ -0,0 +1,183 @@
+use clap::ValueEnum;
+use std::io::Read;
+
+use crate::audio::{ChannelRole, DecodedAudio};
+use crate::decode::{PROGRESS_BLOCK, STDIN_PATH};
+use crate::progress::{Progress, Stage};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum RawFormat {
+    S16le,
+    S24le,
+    S32le,
+    F32le,
+    F64le,
+    U8,
+    Mulaw,
+    Alaw,
+}
+
+impl RawFormat {
+    fn bytes_per_sample(self) -> usize {
+        match self {
+            RawFormat::U8 | RawFormat::Mulaw | RawFormat::Alaw => 1,
+            RawFormat::S16le => 2,
+            RawFormat::S24le => 3,
+            RawFormat::S32le | RawFormat::F32le => 4,
+            RawFormat::F64le => 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+// Layout of headerless PCM data
+#[derive(Clone, Copy, Debug)]
+pub struct RawPcmSpec {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub format: RawFormat,
+    pub endian: Endian,
+}
+
+pub fn read_raw_samples(
+    path: &str,
+    spec: &RawPcmSpec,
+    progress: &dyn Progress,
+) -> Result<DecodedAudio, Box<dyn std::error::Error>> {
+    if spec.sample_rate == 0 || spec.channels == 0 {
+        return Err("Raw input requires a positive sample rate and channel count".into());
+    }
+
+    let mut data = Vec::new();
+    if path == STDIN_PATH {
+        std::io::stdin().lock().read_to_end(&mut data)?;
+    } else {
+        std::fs::File::open(path)?.read_to_end(&mut data)?;
+    }
+
+    let frame_bytes = spec.format.bytes_per_sample() * spec.channels;
+    let total_frames = (data.len() / frame_bytes) as u64;
+    let mut channels = vec![Vec::with_capacity(data.len() / frame_bytes); spec.channels];
+
+    // Trailing bytes that do not form a whole frame are ignored
+    for (i, frame) in data.chunks_exact(frame_bytes).enumerate() {
+        if i.is_multiple_of(PROGRESS_BLOCK) {
+            progress.update(Stage::Decode, i as u64, total_frames);
+        }
+
+        let samples = frame.chunks_exact(spec.format.bytes_per_sample());
+        for (channel, sample) in channels.iter_mut().zip(samples) {
+            channel.push(decode_sample(sample, spec.format, spec.endian));
+        }
+    }
+
+    Ok(DecodedAudio {
+        channels,
+        roles: ChannelRole::defaults(spec.channels),
+        sample_rate: spec.sample_rate,
+    })
+}
+
+// Convert one sample to the range [-1.0, 1.0]
+fn decode_sample(bytes: &[u8], format: RawFormat, endian: Endian) -> f32 {
+    // Normalize to little-endian byte order
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    if endian == Endian::Big {
+        buf[..bytes.len()].reverse();
+    }
+
+    match format {
+        RawFormat::S16le => i16::from_le_bytes([buf[0], buf[1]]) as f32 / 32768.0,
+        RawFormat::S24le => {
+            // Sign-extend the 24-bit value through the top byte of an i32
+            let value = i32::from_le_bytes([0, buf[0], buf[1], buf[2]]) >> 8;
+            value as f32 / 8_388_608.0
+        }
+        RawFormat::S32le => {
+            i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f32 / 2_147_483_648.0
+        }
+        RawFormat::F32le => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        RawFormat::F64le => f64::from_le_bytes(buf) as f32,
+        RawFormat::U8 => (buf[0] as f32 - 128.0) / 128.0,
+        RawFormat::Mulaw => mulaw_to_linear(buf[0]) as f32 / 32768.0,
+        RawFormat::Alaw => alaw_to_linear(buf[0]) as f32 / 32768.0,
+    }
+}
+
+// ITU-T G.711 mu-law expansion
+fn mulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let sign = byte & 0x80;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = byte & 0x0F;
+    let magnitude = ((((mantissa as i32) << 3) + 0x84) << exponent) - 0x84;
+    if sign != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+// ITU-T G.711 A-law expansion
+fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let sign = byte & 0x80;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i32;
+    let magnitude = match exponent {
+        0 => (mantissa << 4) + 8,
+        _ => ((mantissa << 4) + 0x108) << (exponent - 1),
+    };
+    if sign != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn s24_sign_extends() {
+        let decode = |bytes: &[u8]| decode_sample(bytes, RawFormat::S24le, Endian::Little);
+        assert_eq!(decode(&[0x00, 0x00, 0x80]), -1.0);
+        assert_eq!(decode(&[0xFF, 0xFF, 0xFF]), -1.0 / 8_388_608.0);
+        assert_eq!(decode(&[0xFF, 0xFF, 0x7F]), 8_388_607.0 / 8_388_608.0);
+        assert_eq!(
+            decode_sample(&[0x80, 0x00, 0x00], RawFormat::S24le, Endian::Big),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn s16_big_endian_round_trips() {
+        for value in [i16::MIN, -1234, -1, 0, 1, 1234, i16::MAX] {
+            let decoded = decode_sample(&value.to_be_bytes(), RawFormat::S16le, Endian::Big);
+            assert_eq!(decoded, value as f32 / 32768.0, "{}", value);
+        }
+    }
+
+    #[test]
+    fn mulaw_matches_g711() {
+        assert_eq!(mulaw_to_linear(0x00), -32124);
+        assert_eq!(mulaw_to_linear(0x80), 32124);
+        assert_eq!(mulaw_to_linear(0xFF), 0);
+        assert_eq!(mulaw_to_linear(0x7F), 0);
+    }
+
+    #[test]
+    fn alaw_matches_g711() {
+        assert_eq!(alaw_to_linear(0xD5), 8);
+        assert_eq!(alaw_to_linear(0x55), -8);
+        assert_eq!(alaw_to_linear(0xAA), 32256);
+        assert_eq!(alaw_to_linear(0x2A), -32256);
+    }
+}
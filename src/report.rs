@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::config::Settings;
+use crate::lossy::LossyReport;
+use crate::loudness::Loudness;
+use crate::onsets::{OnsetReport, OnsetSummary};
+use crate::pitch::{PitchSummary, PitchTrack};
+use crate::qc::QcReport;
+
+// Result of processing one input, printed as JSON with --json
+#[derive(Serialize)]
+pub struct RunReport {
+    pub input: String,
+    pub output: String,
+    pub sample_rate: u32,
+    pub samples: usize,
+    pub duration_seconds: f64,
+    pub frames: usize,
+    pub fft_size: usize,
+    pub hop_size: usize,
+    pub timings: Timings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loudness: Option<Loudness>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qc: Option<QcReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lossy: Option<LossyReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<PitchTrack>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onsets: Option<OnsetReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_summary: Option<PitchSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onset_summary: Option<OnsetSummary>,
+}
+
+impl RunReport {
+    pub fn new(
+        input: &str,
+        output: &str,
+        samples: usize,
+        sample_rate: u32,
+        settings: &Settings,
+        timings: Timings,
+    ) -> Self {
+        let frames = if samples >= settings.fft_size {
+            (samples - settings.fft_size) / settings.hop_size + 1
+        } else {
+            0
+        };
+
+        RunReport {
+            input: input.to_string(),
+            output: output.to_string(),
+            sample_rate,
+            samples,
+            duration_seconds: samples as f64 / sample_rate as f64,
+            frames,
+            fft_size: settings.fft_size,
+            hop_size: settings.hop_size,
+            timings,
+            loudness: None,
+            qc: None,
+            lossy: None,
+            pitch: None,
+            onsets: None,
+            pitch_summary: None,
+            onset_summary: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BatchReport {
+    pub results: Vec<RunReport>,
+    pub failures: Vec<BatchFailure>,
+}
+
+#[derive(Serialize)]
+pub struct BatchFailure {
+    pub input: String,
+    pub error: String,
+}
+
+// Wall-clock time of each processing stage in milliseconds
+#[derive(Default, Serialize)]
+pub struct Timings {
+    pub decode_ms: f64,
+    pub analysis_ms: f64,
+    pub write_ms: f64,
+    pub total_ms: f64,
+}
+
+// Measures consecutive stages from a common start
+pub struct Stopwatch {
+    start: Instant,
+    last: Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Stopwatch {
+            start: now,
+            last: now,
+        }
+    }
+
+    // Milliseconds since the previous lap (or the start)
+    pub fn lap(&mut self) -> f64 {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+        millis(elapsed)
+    }
+
+    pub fn total(&self) -> f64 {
+        millis(self.start.elapsed())
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub fn print_json<T: Serialize>(report: &T) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => log::error!("Failed to serialize report: {}", e),
+    }
+}
@@ -0,0 +1,65 @@
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_line_segment_mut;
+use std::ops::Range;
+
+use crate::layout::Rect;
+use crate::magnitude_to_db;
+use crate::theme::Palette;
+
+// Long-term spectrum in dB per plotted bin, lowest frequency first
+pub struct AverageSpectrum {
+    /// Power averaged over all frames
+    pub mean: Vec<f32>,
+    /// Highest magnitude reached in any frame
+    pub max: Vec<f32>,
+}
+
+pub fn average_spectrum(frames: &[Vec<f32>], bins: &Range<usize>) -> AverageSpectrum {
+    let mut power = vec![0.0f64; bins.len()];
+    let mut peak = vec![0.0f32; bins.len()];
+    for spectrum in frames {
+        for (i, &magnitude) in spectrum[bins.clone()].iter().enumerate() {
+            power[i] += (magnitude as f64).powi(2);
+            peak[i] = peak[i].max(magnitude);
+        }
+    }
+
+    let count = frames.len().max(1) as f64;
+    AverageSpectrum {
+        mean: power
+            .iter()
+            .map(|&p| magnitude_to_db((p / count).sqrt() as f32))
+            .collect(),
+        max: peak.iter().map(|&m| magnitude_to_db(m)).collect(),
+    }
+}
+
+// Plot dB horizontally over the panel, with rows matching the spectrogram's frequency bins
+pub fn draw_average_spectrum(
+    img: &mut RgbaImage,
+    panel: &Rect,
+    spectrum: &AverageSpectrum,
+    (db_min, db_max): (f32, f32),
+    palette: &Palette,
+) {
+    let x_of = |db: f32| {
+        let normalized = ((db - db_min) / (db_max - db_min)).clamp(0.0, 1.0);
+        panel.x as f32 + normalized * (panel.width as f32 - 1.0)
+    };
+    let y_of = |bin: usize| (panel.bottom() - bin as u32 - 1) as f32;
+
+    let mut draw_curve = |values: &[f32], color: Rgba<u8>| {
+        for (bin, pair) in values.windows(2).enumerate() {
+            draw_line_segment_mut(
+                img,
+                (x_of(pair[0]), y_of(bin)),
+                (x_of(pair[1]), y_of(bin + 1)),
+                color,
+            );
+        }
+    };
+    draw_curve(&spectrum.max, palette.spectrum_max);
+    draw_curve(&spectrum.mean, palette.spectrum_mean);
+
+    crate::draw_outline(img, panel, palette.axes);
+}
@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+use rustfft::{FftPlanner, num_complex::Complex};
+use serde::Deserialize;
+
+use crate::progress::{Progress, Stage};
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Window {
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    Rectangular,
+}
+
+impl Window {
+    pub fn coefficients(self, size: usize) -> Vec<f32> {
+        let n = (size as f32 - 1.0).max(1.0);
+        (0..size)
+            .map(|i| {
+                let x = 2.0 * std::f32::consts::PI * i as f32 / n;
+                match self {
+                    Window::Hann => 0.5 * (1.0 - x.cos()),
+                    Window::Hamming => 0.54 - 0.46 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                    Window::BlackmanHarris => {
+                        0.35875 - 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos()
+                            - 0.01168 * (3.0 * x).cos()
+                    }
+                    Window::Rectangular => 1.0,
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn compute_spectrum(samples: &[f32], window: &[f32]) -> Vec<f32> {
+    let fft_size = window.len();
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(fft_size);
+
+    // 1. Apply window and convert to complex input
+    let mut input: Vec<Complex<f32>> = samples
+        .iter()
+        .take(fft_size)
+        .zip(window.iter())
+        .map(|(s, w)| Complex::new(s * w, 0.0))
+        .collect();
+
+    // 2. Perform FFT
+    fft.process(&mut input);
+
+    // 3. Compute magnitude spectrum
+    input[..fft_size / 2].iter().map(|c| c.norm()).collect()
+}
+
+// Magnitude spectra of every full frame, one vector of fft_size / 2 bins per frame
+pub fn stft_magnitudes(
+    samples: &[f32],
+    window: Window,
+    fft_size: usize,
+    hop_size: usize,
+    progress: &dyn Progress,
+) -> Vec<Vec<f32>> {
+    if samples.len() < fft_size {
+        return Vec::new();
+    }
+
+    let window = window.coefficients(fft_size);
+    let num_frames = (samples.len() - fft_size) / hop_size + 1;
+    (0..num_frames)
+        .map(|i| {
+            progress.update(Stage::Analysis, i as u64 + 1, num_frames as u64);
+            let start = i * hop_size;
+            compute_spectrum(&samples[start..start + fft_size], &window)
+        })
+        .collect()
+}
+
+// Factor for the magnitudes of spectra taken with `window` at which a full-scale sine peaks at 1
+pub fn amplitude_scale(window: &[f32]) -> f32 {
+    2.0 / window.iter().sum::<f32>().max(f32::EPSILON)
+}
+
+pub fn magnitude_to_db(magnitude: f32) -> f32 {
+    let log_mag = if magnitude > 1e-10 {
+        magnitude.log10()
+    } else {
+        -10.0
+    };
+    log_mag * 20.0
+}
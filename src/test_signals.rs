@@ -0,0 +1,46 @@
+// Deterministic signals shared by the analysis tests
+
+use crate::audio::{ChannelRole, DecodedAudio};
+
+// Uniform noise in [-0.5, 0.5) from a fixed linear congruential generator, the same on every run
+pub struct Noise(u64);
+
+impl Noise {
+    pub fn new() -> Self {
+        Noise(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Iterator for Noise {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        Some((self.0 >> 40) as f32 / (1u64 << 24) as f32 - 0.5)
+    }
+}
+
+// White noise peaking at a quarter of full scale
+pub fn white_noise(length: usize) -> Vec<f32> {
+    Noise::new().take(length).map(|noise| noise * 0.5).collect()
+}
+
+pub fn sine(frequency: f64, amplitude: f64, seconds: f64, sample_rate: u32) -> Vec<f32> {
+    (0..(seconds * sample_rate as f64) as usize)
+        .map(|i| {
+            let phase = 2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64;
+            (amplitude * phase.sin()) as f32
+        })
+        .collect()
+}
+
+pub fn mono(samples: Vec<f32>, sample_rate: u32) -> DecodedAudio {
+    DecodedAudio {
+        channels: vec![samples],
+        roles: vec![ChannelRole::Front],
+        sample_rate,
+    }
+}
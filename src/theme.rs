@@ -0,0 +1,115 @@
+use clap::ValueEnum;
+use image::Rgba;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Dark text on a fully transparent background, written as RGBA
+    Transparent,
+}
+
+// A color given as a CSS color string, e.g. "#1e1e1e", "#ffffff80" or "navy"
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub Rgba<u8>);
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        colorgrad::Color::from_html(s)
+            .map(|color| Color(Rgba(color.to_rgba8())))
+            .map_err(|e| format!("Invalid color \"{}\": {}", s, e))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+// Colors of everything drawn around and over the spectrogram
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    pub background: Rgba<u8>,
+    /// Axis lines, tick marks and the colorbar border
+    pub axes: Rgba<u8>,
+    pub text: Rgba<u8>,
+    /// Grid lines are blended halfway into the plot in this color
+    pub grid: Rgba<u8>,
+    pub waveform_peak: Rgba<u8>,
+    pub waveform_rms: Rgba<u8>,
+    pub spectrum_mean: Rgba<u8>,
+    pub spectrum_max: Rgba<u8>,
+    pub loudness_momentary: Rgba<u8>,
+    pub loudness_short_term: Rgba<u8>,
+    pub qc_clipping: Rgba<u8>,
+    pub qc_silence: Rgba<u8>,
+    pub lossy_cutoff: Rgba<u8>,
+    pub pitch: Rgba<u8>,
+    /// Curve of the feature panel
+    pub feature: Rgba<u8>,
+    pub feature_centroid: Rgba<u8>,
+    pub feature_rolloff: Rgba<u8>,
+    pub onset: Rgba<u8>,
+    pub beat: Rgba<u8>,
+}
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Light => Palette {
+                background: Rgba([255, 255, 255, 255]),
+                axes: Rgba([0, 0, 0, 255]),
+                text: Rgba([0, 0, 0, 255]),
+                grid: Rgba([255, 255, 255, 255]),
+                waveform_peak: Rgba([120, 150, 210, 255]),
+                waveform_rms: Rgba([40, 70, 140, 255]),
+                spectrum_mean: Rgba([40, 70, 140, 255]),
+                spectrum_max: Rgba([220, 90, 40, 255]),
+                loudness_momentary: Rgba([120, 150, 210, 255]),
+                loudness_short_term: Rgba([200, 60, 30, 255]),
+                qc_clipping: Rgba([230, 0, 0, 255]),
+                qc_silence: Rgba([150, 150, 150, 255]),
+                lossy_cutoff: Rgba([255, 0, 255, 255]),
+                pitch: Rgba([255, 255, 255, 255]),
+                feature: Rgba([40, 70, 140, 255]),
+                feature_centroid: Rgba([255, 255, 255, 255]),
+                feature_rolloff: Rgba([0, 0, 0, 255]),
+                onset: Rgba([255, 255, 255, 255]),
+                beat: Rgba([0, 0, 0, 255]),
+            },
+            Theme::Dark => Palette {
+                background: Rgba([24, 24, 24, 255]),
+                axes: Rgba([200, 200, 200, 255]),
+                text: Rgba([230, 230, 230, 255]),
+                grid: Rgba([255, 255, 255, 255]),
+                waveform_peak: Rgba([70, 110, 170, 255]),
+                waveform_rms: Rgba([150, 190, 250, 255]),
+                spectrum_mean: Rgba([150, 190, 250, 255]),
+                spectrum_max: Rgba([250, 140, 90, 255]),
+                loudness_momentary: Rgba([70, 110, 170, 255]),
+                loudness_short_term: Rgba([250, 140, 90, 255]),
+                qc_clipping: Rgba([255, 60, 60, 255]),
+                qc_silence: Rgba([120, 120, 120, 255]),
+                lossy_cutoff: Rgba([255, 80, 255, 255]),
+                pitch: Rgba([255, 255, 255, 255]),
+                feature: Rgba([150, 190, 250, 255]),
+                feature_centroid: Rgba([255, 255, 255, 255]),
+                feature_rolloff: Rgba([0, 0, 0, 255]),
+                onset: Rgba([255, 255, 255, 255]),
+                beat: Rgba([0, 0, 0, 255]),
+            },
+            Theme::Transparent => Palette {
+                background: Rgba([255, 255, 255, 0]),
+                ..Theme::Light.palette()
+            },
+        }
+    }
+}
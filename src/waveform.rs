@@ -0,0 +1,74 @@
+use image::RgbaImage;
+use imageproc::drawing::draw_line_segment_mut;
+
+use crate::layout::Rect;
+use crate::theme::Palette;
+
+// Amplitude envelope of the samples covered by one image column
+#[derive(Clone, Copy, Debug)]
+pub struct ColumnEnvelope {
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+// Split the samples evenly over the columns, matching the time axis, which spans the whole input
+pub fn column_envelopes(samples: &[f32], columns: usize) -> Vec<ColumnEnvelope> {
+    (0..columns)
+        .map(|column| {
+            let start = column * samples.len() / columns;
+            let end = ((column + 1) * samples.len() / columns).max(start + 1);
+            let chunk = &samples[start.min(samples.len())..end.min(samples.len())];
+            if chunk.is_empty() {
+                return ColumnEnvelope {
+                    min: 0.0,
+                    max: 0.0,
+                    rms: 0.0,
+                };
+            }
+
+            let (min, max) = chunk.iter().fold((f32::MAX, f32::MIN), |(min, max), &s| {
+                (min.min(s), max.max(s))
+            });
+            let power = chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32;
+            ColumnEnvelope {
+                min,
+                max,
+                rms: power.sqrt(),
+            }
+        })
+        .collect()
+}
+
+// Draw peaks as min-to-max bars with the RMS level on top, full scale at the panel edges
+pub fn draw_waveform(
+    img: &mut RgbaImage,
+    panel: &Rect,
+    envelopes: &[ColumnEnvelope],
+    palette: &Palette,
+) {
+    let center = panel.y as f32 + panel.height as f32 / 2.0;
+    let half_height = (panel.height as f32 - 1.0) / 2.0;
+    let y_of = |amplitude: f32| center - amplitude.clamp(-1.0, 1.0) * half_height;
+
+    for (x, envelope) in envelopes.iter().enumerate() {
+        let x_pos = (panel.x + x as u32) as f32;
+        draw_line_segment_mut(
+            img,
+            (x_pos, y_of(envelope.max)),
+            (x_pos, y_of(envelope.min)),
+            palette.waveform_peak,
+        );
+        draw_line_segment_mut(
+            img,
+            (x_pos, y_of(envelope.rms)),
+            (x_pos, y_of(-envelope.rms)),
+            palette.waveform_rms,
+        );
+    }
+
+    // Zero line and panel border
+    let (left, right) = (panel.x as f32, panel.right() as f32);
+    draw_line_segment_mut(img, (left, center), (right, center), palette.axes);
+    crate::draw_outline(img, panel, palette.axes);
+}